@@ -10,6 +10,7 @@ import subprocess
 import sys
 from pathlib import Path
 import hashlib
+import json
 
 
 def build_with_pyinstaller():
@@ -50,6 +51,7 @@ def build_with_pyinstaller():
     try:
         subprocess.run(cmd, check=True)
         _write_sha256(output_dir / "project-dawn-server")
+        _write_version(output_dir / "project-dawn-server")
         print("\n✓ Build successful!")
         print(f"Executable: {output_dir / 'project-dawn-server'}")
         if sys.platform == "win32":
@@ -89,6 +91,7 @@ def build_with_nuitka():
     try:
         subprocess.run(cmd, check=True)
         _write_sha256(output_dir / "project-dawn-server")
+        _write_version(output_dir / "project-dawn-server")
         print("\n✓ Build successful!")
         print(f"Executable: {output_dir / 'project-dawn-server'}")
         if sys.platform == "win32":
@@ -138,5 +141,20 @@ def _write_sha256(executable_path: Path) -> None:
     print(f"Checksum written: {checksum_path}")
 
 
+def _write_version(executable_path: Path) -> None:
+    """Record the release version next to the sidecar executable (read by sidecar_version)."""
+    candidates = [executable_path, executable_path.with_suffix(".exe")]
+    target = next((p for p in candidates if p.exists()), None)
+    if target is None:
+        print("Warning: sidecar executable not found for version file")
+        return
+
+    package_json = Path(__file__).parent.parent / "package.json"
+    version = json.loads(package_json.read_text())["version"]
+    version_path = target.with_name(f"{target.name}.version")
+    version_path.write_text(f"{version}\n")
+    print(f"Version written: {version_path}")
+
+
 if __name__ == "__main__":
     main()
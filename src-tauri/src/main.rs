@@ -1,383 +1,7839 @@
 // Prevents additional console window on Windows in release builds
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use ed25519_dalek::{Signature, VerifyingKey};
 use hex::FromHex;
-use sha2::{Digest, Sha256};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::fs;
 use std::fs::File;
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use sysinfo::{Components, System, SystemExt};
+use std::time::{Duration, Instant};
+use sysinfo::{Component, Components, Disks, Networks, Pid, System};
 use tauri::api::process::{Command, CommandChild, CommandEvent};
+use tauri::async_runtime::Receiver;
 use tauri::{Manager, State};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
+use tracing::{error, info, warn, Instrument};
+
+// The bundled sidecar; commands that take no name act on it.
+const DEFAULT_SIDECAR_NAME: &str = "project-dawn-server";
+const DEFAULT_SIDECAR_PORT: u16 = 8000;
+// The sidecar's HTTP API (/health/ready, /metrics).
+const DEFAULT_METRICS_PORT: u16 = 9090;
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_SIDECAR_RESTARTS: u32 = 5;
+const MAX_RESTART_BACKOFF_SECS: u64 = 30;
+// A sidecar that stays up this long is considered stable and its restart budget is refilled.
+const STABLE_RUN_SECS: u64 = 60;
+const DEFAULT_SHUTDOWN_TIMEOUT_MS: u64 = 5000;
+const DEFAULT_RESTART_READY_TIMEOUT_MS: u64 = 30_000;
+// Upper bound on a whole start (verify, spawn and any readiness wait) before it's abandoned.
+const DEFAULT_START_TIMEOUT_MS: u64 = 30_000;
+const STDERR_TAIL_LINES: usize = 20;
+// Hex-encoded Ed25519 public key used to verify `<binary>.sig`, baked in at build time.
+const SIDECAR_SIGNING_PUBKEY: Option<&str> = option_env!("PROJECT_DAWN_SIDECAR_PUBKEY");
+const SIDECAR_LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+const SIDECAR_LOG_KEEP: usize = 3;
+// One hour of samples at the default 5s interval.
+const RESOURCE_HISTORY_CAP: usize = 720;
+const DEFAULT_RESOURCE_INTERVAL_SECS: u64 = 5;
+const MANIFEST_BACKUP_KEEP: usize = 3;
+// Automatic feed compaction is opt-in through `PROJECT_DAWN_FEED_MAX_BYTES`: the sidecar owns
+// the feed, and a rewrite can't be coordinated with a sidecar that keeps the file open.
+const DEFAULT_FEED_MAX_BYTES: u64 = 0;
+// How much of each log goes into a diagnostics bundle.
+const DIAGNOSTICS_LOG_LINES: usize = 2000;
+// feed_append batching: writes within this window go out together, at most this many lines
+// per event.
+const FEED_EMIT_DEBOUNCE: Duration = Duration::from_millis(200);
+const FEED_EVENT_MAX_LINES: usize = 500;
+const MIGRATION_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// An atomic write shows up as create + rename; wait this long for a path to go quiet.
+const FILE_CHANGE_DEBOUNCE: Duration = Duration::from_millis(250);
+// A read that races the sidecar's rename of the same file is retried this many times.
+const READ_RETRY_ATTEMPTS: u32 = 3;
+const READ_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone)]
+enum AppError {
+    NotFound(String),
+    PermissionDenied(String),
+    Io(String),
+    IntegrityFailed(String),
+    SidecarUnavailable(String),
+    InvalidInput(String),
+    // Refused because maintenance mode is on; the message carries the reason.
+    Maintenance(String),
+    // Refused because the app runs as an observer (PROJECT_DAWN_READONLY).
+    ReadOnly(String),
+    // A caller-supplied path that would resolve outside the data root.
+    InvalidPath(String),
+    // An update couldn't be undone; the message names the `.rollback` files left behind.
+    RollbackFailed(String),
+}
+
+impl AppError {
+    fn io(context: impl fmt::Display, err: std::io::Error) -> Self {
+        let message = format!("{context}: {err}");
+        match err.kind() {
+            ErrorKind::NotFound => AppError::NotFound(message),
+            ErrorKind::PermissionDenied => AppError::PermissionDenied(message),
+            _ => AppError::Io(message),
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "not_found",
+            AppError::PermissionDenied(_) => "permission_denied",
+            AppError::Io(_) => "io",
+            AppError::IntegrityFailed(_) => "integrity_failed",
+            AppError::SidecarUnavailable(_) => "sidecar_unavailable",
+            AppError::InvalidInput(_) => "invalid_input",
+            AppError::Maintenance(_) => "maintenance",
+            AppError::ReadOnly(_) => "read_only",
+            AppError::InvalidPath(_) => "invalid_path",
+            AppError::RollbackFailed(_) => "rollback_failed",
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            AppError::NotFound(message)
+            | AppError::PermissionDenied(message)
+            | AppError::Io(message)
+            | AppError::IntegrityFailed(message)
+            | AppError::SidecarUnavailable(message)
+            | AppError::InvalidInput(message)
+            | AppError::Maintenance(message)
+            | AppError::ReadOnly(message)
+            | AppError::InvalidPath(message)
+            | AppError::RollbackFailed(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+// Commands reject with `{ code, message }` so the frontend can branch on a stable code.
+impl Serialize for AppError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut error = serializer.serialize_struct("AppError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", self.message())?;
+        error.end()
+    }
+}
 
 struct SidecarState {
+    // Binary name under `sidecar/`, also the key in SidecarRegistry.
+    name: String,
     process: Option<CommandChild>,
     port: u16,
-    health_task_running: bool,
-    resource_task_running: bool,
+    // 0 lets the OS choose; only the default sidecar claims the well-known ports.
+    preferred_port: u16,
+    preferred_metrics_port: u16,
+    // Set while the monitor task runs. Atomic so a finished task can clear it from Drop
+    // without waiting for this state's lock.
+    health_task_running: Arc<AtomicBool>,
+    resource_task_running: Arc<AtomicBool>,
+    // Held by apply_sidecar_update while the binary is being swapped; starts are refused.
+    updating: Arc<AtomicBool>,
+    stop_requested: bool,
+    restart_attempts: u32,
+    max_restarts: u32,
+    exit_notify: Arc<Notify>,
+    health: HealthStatus,
+    health_last_logged: Option<Instant>,
+    reported_unhealthy: bool,
+    resource_interval_secs: u64,
+    resource_paused: bool,
+    // The snapshot last written to resource_state.json, so readers can skip the disk.
+    last_resource: Option<serde_json::Value>,
+    // Set while last_resource is the previous run's snapshot, until the first fresh sample.
+    resource_stale: bool,
+    // Attached to a sidecar we didn't spawn (dev mode); never verified, restarted or killed.
+    external: bool,
+    extra_env: HashMap<String, String>,
+    metrics_port: u16,
+    last_exit: Option<SidecarExit>,
+    // Bumped on every spawn or attach so stale readiness watchers can tell they're outdated.
+    launch_id: u64,
+    ready: bool,
+    ready_notify: Arc<Notify>,
+    // Woken by stop requests so a start that's still in progress gives up.
+    start_cancel: Arc<Notify>,
+    // When the current process was spawned or attached; None while stopped.
+    started_at: Option<Instant>,
 }
 
 impl SidecarState {
-    fn new() -> Self {
+    fn new(name: &str) -> Self {
+        let (preferred_port, preferred_metrics_port) = if name == DEFAULT_SIDECAR_NAME {
+            (DEFAULT_SIDECAR_PORT, DEFAULT_METRICS_PORT)
+        } else {
+            (0, 0)
+        };
         Self {
+            name: name.to_string(),
             process: None,
-            port: 8000,
-            health_task_running: false,
-            resource_task_running: false,
+            port: DEFAULT_SIDECAR_PORT,
+            preferred_port,
+            preferred_metrics_port,
+            health_task_running: Arc::new(AtomicBool::new(false)),
+            resource_task_running: Arc::new(AtomicBool::new(false)),
+            updating: Arc::new(AtomicBool::new(false)),
+            stop_requested: false,
+            restart_attempts: 0,
+            max_restarts: MAX_SIDECAR_RESTARTS,
+            exit_notify: Arc::new(Notify::new()),
+            health: HealthStatus::default(),
+            health_last_logged: None,
+            reported_unhealthy: false,
+            resource_interval_secs: DEFAULT_RESOURCE_INTERVAL_SECS,
+            resource_paused: false,
+            last_resource: None,
+            resource_stale: false,
+            external: false,
+            extra_env: HashMap::new(),
+            metrics_port: DEFAULT_METRICS_PORT,
+            last_exit: None,
+            launch_id: 0,
+            ready: false,
+            ready_notify: Arc::new(Notify::new()),
+            start_cancel: Arc::new(Notify::new()),
+            started_at: None,
+        }
+    }
+
+    fn begin_launch(&mut self) -> u64 {
+        self.launch_id += 1;
+        self.ready = false;
+        self.started_at = Some(Instant::now());
+        self.launch_id
+    }
+
+    fn resource_snapshot(&self) -> Option<serde_json::Value> {
+        let mut snapshot = self.last_resource.clone()?;
+        if let Some(fields) = snapshot.as_object_mut() {
+            fields.insert("stale".to_string(), serde_json::json!(self.resource_stale));
+        }
+        Some(snapshot)
+    }
+
+    fn uptime_secs(&self) -> Option<u64> {
+        if self.mode() == SidecarMode::Stopped {
+            return None;
+        }
+        self.started_at.map(|at| at.elapsed().as_secs())
+    }
+
+    fn mode(&self) -> SidecarMode {
+        if self.external {
+            SidecarMode::External
+        } else if self.process.is_some() && self.ready {
+            SidecarMode::Running
+        } else if self.process.is_some() {
+            SidecarMode::Loading
+        } else {
+            SidecarMode::Stopped
         }
     }
 }
 
-#[tauri::command]
-async fn check_sidecar_health(port: u16) -> Result<bool, String> {
-    // Simple health check - try to connect to the WebSocket port
-    use tokio::net::TcpStream;
-    
-    match tokio::time::timeout(
-        Duration::from_secs(2),
-        TcpStream::connect(format!("127.0.0.1:{}", port))
-    ).await {
-        Ok(Ok(_)) => Ok(true),
-        Ok(Err(_)) => Ok(false),
-        Err(_) => Ok(false),
+// Every sidecar the app knows about, keyed by name. The default one is also managed on its own
+// as `Arc<Mutex<SidecarState>>`, which is what the resource monitor and the name-less commands
+// use.
+struct SidecarRegistry(std::sync::Mutex<HashMap<String, Arc<Mutex<SidecarState>>>>);
+
+impl SidecarRegistry {
+    fn new(default: Arc<Mutex<SidecarState>>) -> Self {
+        let mut sidecars = HashMap::new();
+        sidecars.insert(DEFAULT_SIDECAR_NAME.to_string(), default);
+        Self(std::sync::Mutex::new(sidecars))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<Mutex<SidecarState>>>> {
+        match self.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<Arc<Mutex<SidecarState>>> {
+        self.lock().get(name).cloned()
+    }
+
+    fn get_or_insert(&self, name: &str) -> Arc<Mutex<SidecarState>> {
+        self.lock()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(SidecarState::new(name))))
+            .clone()
+    }
+
+    fn all(&self) -> Vec<Arc<Mutex<SidecarState>>> {
+        self.lock().values().cloned().collect()
     }
 }
 
-fn sidecar_filename() -> &'static str {
-    if cfg!(windows) {
-        "project-dawn-server.exe"
+// Names become file names under `sidecar/`, so nothing that could leave that directory.
+fn validate_sidecar_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
     } else {
-        "project-dawn-server"
+        Err(AppError::InvalidInput(format!(
+            "Invalid sidecar name: {:?}",
+            name
+        )))
     }
 }
 
-fn resolve_sidecar_paths(app_handle: &tauri::AppHandle) -> Option<(PathBuf, PathBuf)> {
-    let resource_dir = app_handle.path_resolver().resource_dir()?;
-    let sidecar_path = resource_dir.join("sidecar").join(sidecar_filename());
-    let checksum_path = sidecar_path.with_file_name(format!(
-        "{}.sha256",
-        sidecar_path.file_name()?.to_string_lossy()
-    ));
-    Some((sidecar_path, checksum_path))
+fn sidecar_name(name: Option<String>) -> Result<String, AppError> {
+    let name = name.unwrap_or_else(|| DEFAULT_SIDECAR_NAME.to_string());
+    validate_sidecar_name(&name)?;
+    Ok(name)
 }
 
-fn read_checksum(checksum_path: &PathBuf) -> Result<Vec<u8>, String> {
-    let contents = std::fs::read_to_string(checksum_path)
-        .map_err(|e| format!("Failed to read checksum: {e}"))?;
-    let digest_hex = contents
-        .split_whitespace()
-        .next()
-        .ok_or_else(|| "Checksum file missing digest".to_string())?;
-    let bytes = Vec::from_hex(digest_hex)
-        .map_err(|e| format!("Invalid checksum format: {e}"))?;
-    Ok(bytes)
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+const HEALTH_LOG_INTERVAL: Duration = Duration::from_secs(60);
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+// RFC 6455 sample nonce; the server only needs a syntactically valid key.
+const WS_PROBE_KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "status", content = "detail", rename_all = "snake_case")]
+enum HealthProbe {
+    Healthy,
+    Unreachable(String),
+    Timeout,
+    BadResponse(String),
+}
+
+impl HealthProbe {
+    fn is_healthy(&self) -> bool {
+        matches!(self, HealthProbe::Healthy)
+    }
 }
 
-fn verify_sidecar_integrity(app_handle: &tauri::AppHandle) -> Result<(), String> {
-    let (sidecar_path, checksum_path) = resolve_sidecar_paths(app_handle)
-        .ok_or_else(|| "Failed to resolve sidecar path".to_string())?;
+#[derive(Serialize, Clone, Debug, Default)]
+struct HealthStatus {
+    reachable: bool,
+    latency_ms: Option<u64>,
+    last_success_epoch: Option<i64>,
+    consecutive_failures: u32,
+    // Never reset by recovery; exported as a Prometheus counter.
+    total_failures: u64,
+    // Whether the shared UI connection (see SidecarSocket) is currently open.
+    socket_connected: bool,
+}
 
-    if !sidecar_path.exists() {
-        return Err(format!("Sidecar executable not found: {:?}", sidecar_path));
+impl HealthStatus {
+    fn record(&mut self, probe: &HealthProbe, latency: Duration) {
+        self.reachable = probe.is_healthy();
+        if self.reachable {
+            self.latency_ms = Some(latency.as_millis() as u64);
+            self.last_success_epoch = Some(chrono::Utc::now().timestamp());
+            self.consecutive_failures = 0;
+        } else {
+            self.latency_ms = None;
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+            self.total_failures += 1;
+        }
     }
-    if !checksum_path.exists() {
-        return Err(format!("Sidecar checksum not found: {:?}", checksum_path));
+}
+
+async fn websocket_handshake(host: &str, port: u16, path: &str) -> HealthProbe {
+    let request = format!(
+        "GET {path} HTTP/1.1\r\nHost: {host}:{port}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {WS_PROBE_KEY}\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    );
+    let status_line = match request_status_line(host, port, &request).await {
+        Ok(status_line) => status_line,
+        Err(probe) => return probe,
+    };
+    match status_line.split_whitespace().nth(1) {
+        Some("101") | Some("200") => HealthProbe::Healthy,
+        _ => HealthProbe::BadResponse(status_line),
     }
+}
 
-    let expected = read_checksum(&checksum_path)?;
-    let mut file = File::open(&sidecar_path)
-        .map_err(|e| format!("Failed to open sidecar: {e}"))?;
-    let mut hasher = Sha256::new();
-    let mut buffer = [0u8; 1024 * 1024];
-    loop {
-        let read = file.read(&mut buffer).map_err(|e| format!("Failed to read sidecar: {e}"))?;
-        if read == 0 {
-            break;
+// Readiness comes from the sidecar's HTTP API: 200 once the node has started, 503 before.
+async fn readiness_check(port: u16) -> HealthProbe {
+    let request = format!(
+        "GET /health/ready HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nConnection: close\r\n\r\n"
+    );
+    let status_line = match request_status_line("127.0.0.1", port, &request).await {
+        Ok(status_line) => status_line,
+        Err(probe) => return probe,
+    };
+    match status_line.split_whitespace().nth(1) {
+        Some("200") => HealthProbe::Healthy,
+        _ => HealthProbe::BadResponse(status_line),
+    }
+}
+
+async fn request_status_line(host: &str, port: u16, request: &str) -> Result<String, HealthProbe> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    let mut stream = TcpStream::connect((host, port))
+        .await
+        .map_err(|e| HealthProbe::Unreachable(e.to_string()))?;
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| HealthProbe::Unreachable(e.to_string()))?;
+
+    // Only the status line matters; a listening-but-wedged server never gets this far.
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 512];
+    while !response.windows(2).any(|window| window == b"\r\n") && response.len() < 4096 {
+        match stream.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(read) => response.extend_from_slice(&buffer[..read]),
+            Err(e) => return Err(HealthProbe::Unreachable(e.to_string())),
         }
-        hasher.update(&buffer[..read]);
     }
-    let actual = hasher.finalize().to_vec();
-    if actual != expected {
-        return Err("Sidecar checksum mismatch".to_string());
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if status_line.is_empty() {
+        return Err(HealthProbe::BadResponse(
+            "Connection closed before handshake".to_string(),
+        ));
     }
-    Ok(())
+    Ok(status_line)
 }
 
-async fn start_health_monitor(state: Arc<Mutex<SidecarState>>) {
-    let mut guard = state.lock().await;
-    if guard.health_task_running {
-        return;
+async fn probe_websocket(host: &str, port: u16, path: &str, timeout: Duration) -> HealthProbe {
+    match tokio::time::timeout(timeout, websocket_handshake(host, port, path)).await {
+        Ok(probe) => probe,
+        Err(_) => HealthProbe::Timeout,
     }
-    guard.health_task_running = true;
-    let port = guard.port;
-    drop(guard);
+}
 
-    tauri::async_runtime::spawn(async move {
-        loop {
-            tokio::time::sleep(Duration::from_secs(5)).await;
-            match check_sidecar_health(port).await {
-                Ok(true) => {}
-                Ok(false) | Err(_) => {
-                    eprintln!("[Tauri] Sidecar health check failed on port {}", port);
-                }
-            }
-        }
-    });
+async fn probe_sidecar(port: u16, timeout: Duration) -> HealthProbe {
+    probe_websocket("127.0.0.1", port, "/", timeout).await
 }
 
-#[tauri::command]
-async fn sidecar_status(state: State<'_, Arc<Mutex<SidecarState>>>) -> Result<bool, String> {
-    let guard = state.lock().await;
-    Ok(guard.process.is_some())
+async fn probe_ready(metrics_port: u16) -> HealthProbe {
+    match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, readiness_check(metrics_port)).await {
+        Ok(probe) => probe,
+        Err(_) => HealthProbe::Timeout,
+    }
 }
 
-#[tauri::command]
-async fn start_sidecar(
-    state: State<'_, Arc<Mutex<SidecarState>>>,
-    app: tauri::AppHandle,
-) -> Result<bool, String> {
-    let mut guard = state.lock().await;
-    if guard.process.is_some() {
-        return Ok(true);
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+struct HealthConfig {
+    interval_secs: u64,
+    timeout_secs: u64,
+    // Consecutive failed checks before the sidecar counts as unhealthy.
+    failure_threshold: u32,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 5,
+            timeout_secs: HEALTH_CHECK_TIMEOUT.as_secs(),
+            failure_threshold: 3,
+        }
     }
+}
 
-    if let Err(err) = verify_sidecar_integrity(&app) {
-        return Err(err);
+impl HealthConfig {
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
     }
 
-    let data_root = data_root(&app);
-    let (mut rx, child) = Command::new_sidecar("project-dawn-server")
-        .map_err(|e| format!("Failed to configure sidecar: {e}"))?
-        .env("PROJECT_DAWN_DATA_ROOT", data_root.to_string_lossy().to_string())
-        .spawn()
-        .map_err(|e| format!("Failed to start sidecar: {e}"))?;
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.max(1))
+    }
+}
 
-    guard.process = Some(child);
-    drop(guard);
+fn health_config_path(data_root: &Path) -> PathBuf {
+    data_root.join("mesh").join("health_config.json")
+}
 
-    tauri::async_runtime::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            match event {
-                CommandEvent::Stdout(line) => println!("[sidecar] {}", line),
-                CommandEvent::Stderr(line) => eprintln!("[sidecar] {}", line),
-                CommandEvent::Error(err) => eprintln!("[sidecar] error: {}", err),
-                _ => {}
-            }
+fn load_health_config(data_root: &Path) -> HealthConfig {
+    let path = health_config_path(data_root);
+    match read_optional_file(path.clone()) {
+        Ok(Some(contents)) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            warn!("Invalid {}: {e}; using defaults", path.display());
+            HealthConfig::default()
+        }),
+        Ok(None) => HealthConfig::default(),
+        Err(err) => {
+            warn!("{}; using default health config", err);
+            HealthConfig::default()
         }
-    });
+    }
+}
 
-    start_health_monitor(state.inner().clone()).await;
-    Ok(true)
+#[tauri::command]
+fn get_health_config(app: tauri::AppHandle) -> Result<HealthConfig, AppError> {
+    Ok(load_health_config(&data_root(&app)))
 }
 
+// The monitor re-reads the file every cycle, so changes apply from the next check.
 #[tauri::command]
-async fn stop_sidecar(state: State<'_, Arc<Mutex<SidecarState>>>) -> Result<bool, String> {
-    let mut guard = state.lock().await;
-    if let Some(child) = guard.process.take() {
-        let _ = child.kill();
-        Ok(true)
+fn set_health_config(app: tauri::AppHandle, config: HealthConfig) -> Result<(), AppError> {
+    ensure_writable(&app)?;
+    if config.interval_secs == 0 || config.timeout_secs == 0 || config.failure_threshold == 0 {
+        return Err(AppError::InvalidInput(
+            "interval_secs, timeout_secs and failure_threshold must be at least 1".to_string(),
+        ));
+    }
+    let payload = serde_json::to_string_pretty(&config)
+        .map_err(|e| AppError::InvalidInput(format!("Failed to serialize health config: {e}")))?;
+    write_json_atomic(&health_config_path(&data_root(&app)), &payload)
+}
+
+#[tauri::command]
+async fn check_sidecar_health(app: tauri::AppHandle, port: u16) -> Result<HealthProbe, AppError> {
+    let root = data_root(&app);
+    let config = off_runtime(move || load_health_config(&root)).await?;
+    Ok(probe_sidecar(port, config.timeout()).await)
+}
+
+fn sidecar_filename(name: &str) -> String {
+    if cfg!(windows) {
+        format!("{name}.exe")
     } else {
-        Ok(false)
+        name.to_string()
     }
 }
 
-fn data_root(app: &tauri::AppHandle) -> PathBuf {
-    if let Ok(override_path) = std::env::var("PROJECT_DAWN_DATA_ROOT") {
-        return PathBuf::from(override_path);
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum HashAlgorithm {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    // Strongest first.
+    const PREFERENCE: [HashAlgorithm; 3] = [
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Blake3,
+        HashAlgorithm::Sha256,
+    ];
+
+    fn extension(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    // The multi-file manifest release tooling publishes (`sha256sum` output for many files).
+    fn manifest_name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA256SUMS",
+            HashAlgorithm::Sha512 => "SHA512SUMS",
+            HashAlgorithm::Blake3 => "B3SUMS",
+        }
+    }
+
+    fn from_manifest_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        Self::PREFERENCE
+            .into_iter()
+            .find(|algo| algo.manifest_name() == name)
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        if let Some(algo) = Self::from_manifest_path(path) {
+            return Some(algo);
+        }
+        let ext = path.extension()?.to_str()?;
+        Self::PREFERENCE
+            .into_iter()
+            .find(|algo| algo.extension().eq_ignore_ascii_case(ext))
+    }
+
+    fn is_supported(self) -> bool {
+        match self {
+            HashAlgorithm::Sha256 | HashAlgorithm::Sha512 => true,
+            HashAlgorithm::Blake3 => cfg!(feature = "blake3"),
+        }
     }
-    tauri::api::path::app_data_dir(&app.config())
-        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
 }
 
-fn write_json_atomic(path: &PathBuf, payload: &str) -> Result<(), String> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+// `PROJECT_DAWN_SIDECAR_PATH` points the default sidecar at an arbitrary binary, e.g. a QA build,
+// which is spawned directly instead of the bundled externalBin.
+fn sidecar_path_override(name: &str) -> Option<PathBuf> {
+    if name != DEFAULT_SIDECAR_NAME {
+        return None;
     }
-    let tmp_path = path.with_extension("tmp");
-    let mut handle = File::create(&tmp_path)
-        .map_err(|e| format!("Failed to create {}: {e}", tmp_path.display()))?;
-    handle
-        .write_all(payload.as_bytes())
-        .map_err(|e| format!("Failed to write {}: {e}", tmp_path.display()))?;
-    handle
-        .write_all(b"\n")
-        .map_err(|e| format!("Failed to write newline: {e}"))?;
-    handle.flush().map_err(|e| format!("Failed to flush: {e}"))?;
-    handle
-        .sync_all()
-        .map_err(|e| format!("Failed to sync: {e}"))?;
-    fs::rename(&tmp_path, path)
-        .map_err(|e| format!("Failed to move {}: {e}", path.display()))?;
-    Ok(())
+    std::env::var_os("PROJECT_DAWN_SIDECAR_PATH")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
 }
 
-fn read_optional_file(path: PathBuf) -> Result<Option<String>, String> {
-    if !path.exists() {
-        return Ok(None);
+// `PROJECT_DAWN_SIDECAR_PATH`, then `PROJECT_DAWN_SIDECAR_DIR`, win when set. Otherwise the
+// bundled `sidecar/` resource directory, then `sidecar/` next to the executable and the
+// executable's own directory, for AppImage and portable layouts where the resource dir resolves
+// somewhere unexpected.
+fn locate_sidecar_binary(app_handle: &tauri::AppHandle, name: &str) -> Result<PathBuf, AppError> {
+    if let Some(path) = sidecar_path_override(name) {
+        if path.exists() {
+            return Ok(path);
+        }
+        return Err(AppError::NotFound(format!(
+            "PROJECT_DAWN_SIDECAR_PATH is set but {} does not exist",
+            path.display()
+        )));
     }
-    fs::read_to_string(&path)
-        .map(Some)
-        .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+    let file_name = sidecar_filename(name);
+    if let Some(dir) = std::env::var_os("PROJECT_DAWN_SIDECAR_DIR") {
+        let path = PathBuf::from(dir).join(&file_name);
+        if path.exists() {
+            return Ok(path);
+        }
+        return Err(AppError::NotFound(format!(
+            "PROJECT_DAWN_SIDECAR_DIR is set but {} does not exist",
+            path.display()
+        )));
+    }
+
+    let mut candidates = Vec::new();
+    let mut problems = Vec::new();
+    match app_handle.path_resolver().resource_dir() {
+        Some(dir) => candidates.push(dir.join("sidecar").join(&file_name)),
+        None => problems.push("resource directory unavailable".to_string()),
+    }
+    match std::env::current_exe() {
+        Ok(exe) => {
+            if let Some(dir) = exe.parent() {
+                candidates.push(dir.join("sidecar").join(&file_name));
+                candidates.push(dir.join(&file_name));
+            }
+        }
+        Err(err) => problems.push(format!("executable path unavailable ({})", err)),
+    }
+    if let Some(found) = candidates.iter().position(|path| path.exists()) {
+        if found > 0 {
+            info!(
+                "Sidecar {} found at {} (not found at {})",
+                name,
+                candidates[found].display(),
+                candidates[..found]
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        return Ok(candidates.swap_remove(found));
+    }
+
+    if !candidates.is_empty() {
+        let tried: Vec<String> = candidates
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        problems.insert(0, format!("tried {}", tried.join(", ")));
+    }
+    let message = format!(
+        "Sidecar executable {} not found: {}",
+        file_name,
+        problems.join("; ")
+    );
+    warn!("{}", message);
+    Err(AppError::NotFound(message))
 }
 
-#[tauri::command]
-fn get_manifest(app: tauri::AppHandle) -> Result<Option<String>, String> {
-    let path = data_root(&app).join("vault").join("manifest.json");
-    read_optional_file(path)
+fn resolve_sidecar_paths(
+    app_handle: &tauri::AppHandle,
+    name: &str,
+) -> Result<(PathBuf, PathBuf), AppError> {
+    let sidecar_path = locate_sidecar_binary(app_handle, name)?;
+    let checksum_path = checksum_path_for(&sidecar_path);
+    Ok((sidecar_path, checksum_path))
 }
 
-#[tauri::command]
-fn get_peers(app: tauri::AppHandle) -> Result<Option<String>, String> {
-    let path = data_root(&app).join("mesh").join("peers.json");
-    read_optional_file(path)
+// Where the checksum for `sidecar_path` is (or would be, when there's none).
+fn checksum_path_for(sidecar_path: &Path) -> PathBuf {
+    // An override binary can be named anything; its checksum follows its name.
+    let file_name = sidecar_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    // `<binary>.<algo>` wins; otherwise a `SHA256SUMS`-style manifest that lists the binary.
+    let checksum_for = |algo: HashAlgorithm| {
+        let single = sidecar_path.with_file_name(format!("{}.{}", file_name, algo.extension()));
+        if single.exists() {
+            return Some(single);
+        }
+        let manifest = sidecar_path.with_file_name(algo.manifest_name());
+        let contents = fs::read_to_string(&manifest).ok()?;
+        checksum_manifest_entry(&contents, &file_name).map(|_| manifest)
+    };
+
+    // Prefer the strongest checksum this build can verify, but still surface an
+    // unsupported one so verification fails loudly instead of reporting "missing".
+    let present: Vec<(HashAlgorithm, PathBuf)> = HashAlgorithm::PREFERENCE
+        .into_iter()
+        .filter_map(|algo| Some((algo, checksum_for(algo)?)))
+        .collect();
+    present
+        .iter()
+        .find(|(algo, _)| algo.is_supported())
+        .or_else(|| present.first())
+        .map(|(_, path)| path.clone())
+        .unwrap_or_else(|| {
+            sidecar_path.with_file_name(format!(
+                "{}.{}",
+                file_name,
+                HashAlgorithm::Sha256.extension()
+            ))
+        })
 }
 
-#[tauri::command]
-fn get_feed(app: tauri::AppHandle, limit: usize) -> Result<Vec<String>, String> {
-    let path = data_root(&app).join("mesh").join("agent_feed.jsonl");
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
-    let contents = fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
-    let mut lines: Vec<String> = contents.lines().map(|line| line.to_string()).collect();
-    if lines.len() > limit {
-        lines = lines.split_off(lines.len() - limit);
-    }
-    Ok(lines)
+// The digest listed for `file_name` in a manifest of `<hex>  <filename>` lines. A `*` before the
+// name marks binary mode, and a leading directory is ignored.
+fn checksum_manifest_entry<'a>(contents: &'a str, file_name: &str) -> Option<&'a str> {
+    contents.lines().find_map(|line| {
+        let (digest, listed) = line.trim().split_once(char::is_whitespace)?;
+        let listed = listed.trim_start().trim_start_matches('*');
+        (Path::new(listed).file_name()? == file_name).then_some(digest)
+    })
 }
 
-#[tauri::command]
-fn get_resource_state(app: tauri::AppHandle) -> Result<Option<String>, String> {
-    let path = data_root(&app).join("mesh").join("resource_state.json");
-    read_optional_file(path)
+// Reads either a single-file checksum (`<hex>` or `<hex>  <filename>`) or, for a manifest such
+// as `SHA256SUMS`, the line for `file_name`.
+fn read_checksum(checksum_path: &Path, file_name: &str) -> Result<Vec<u8>, AppError> {
+    let contents = std::fs::read_to_string(checksum_path)
+        .map_err(|e| AppError::io("Failed to read checksum", e))?;
+    let digest_hex = if HashAlgorithm::from_manifest_path(checksum_path).is_some() {
+        checksum_manifest_entry(&contents, file_name).ok_or_else(|| {
+            AppError::IntegrityFailed(format!(
+                "{} has no entry for {}",
+                checksum_path.display(),
+                file_name
+            ))
+        })?
+    } else {
+        contents
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| AppError::IntegrityFailed("Checksum file missing digest".to_string()))?
+    };
+    let bytes = Vec::from_hex(digest_hex)
+        .map_err(|e| AppError::IntegrityFailed(format!("Invalid checksum format: {e}")))?;
+    Ok(bytes)
 }
 
-fn read_battery_status() -> (Option<f32>, Option<bool>) {
-    let base = PathBuf::from("/sys/class/power_supply");
-    if !base.exists() {
-        return (None, None);
+fn stream_file(path: &Path, mut update: impl FnMut(&[u8])) -> Result<(), AppError> {
+    let mut file = File::open(path)
+        .map_err(|e| AppError::io(format!("Failed to open {}", path.display()), e))?;
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+        if read == 0 {
+            break;
+        }
+        update(&buffer[..read]);
     }
+    Ok(())
+}
 
-    let mut battery_pct = None;
-    let mut on_ac = None;
+fn hash_file(path: &Path, algo: HashAlgorithm) -> Result<Vec<u8>, AppError> {
+    hash_file_with_progress(path, algo, |_| {})
+}
 
-    if let Ok(entries) = fs::read_dir(base) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            let kind = fs::read_to_string(path.join("type")).unwrap_or_default();
-            let kind = kind.trim();
-            if kind == "Battery" {
-                let capacity = fs::read_to_string(path.join("capacity")).unwrap_or_default();
-                if let Ok(value) = capacity.trim().parse::<f32>() {
-                    battery_pct = Some(value);
-                }
-            } else if kind == "Mains" || kind == "AC" {
-                let online = fs::read_to_string(path.join("online")).unwrap_or_default();
-                if let Ok(value) = online.trim().parse::<u8>() {
-                    on_ac = Some(value == 1);
-                }
-            }
+fn hash_file_with_progress(
+    path: &Path,
+    algo: HashAlgorithm,
+    mut progress: impl FnMut(u64),
+) -> Result<Vec<u8>, AppError> {
+    match algo {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            stream_file(path, |chunk| {
+                hasher.update(chunk);
+                progress(chunk.len() as u64);
+            })?;
+            Ok(hasher.finalize().to_vec())
         }
+        HashAlgorithm::Sha512 => {
+            let mut hasher = Sha512::new();
+            stream_file(path, |chunk| {
+                hasher.update(chunk);
+                progress(chunk.len() as u64);
+            })?;
+            Ok(hasher.finalize().to_vec())
+        }
+        #[cfg(feature = "blake3")]
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            stream_file(path, |chunk| {
+                hasher.update(chunk);
+                progress(chunk.len() as u64);
+            })?;
+            Ok(hasher.finalize().as_bytes().to_vec())
+        }
+        #[cfg(not(feature = "blake3"))]
+        HashAlgorithm::Blake3 => {
+            let _ = &mut progress;
+            Err(AppError::IntegrityFailed(
+                "BLAKE3 checksums are not supported by this build (enable the `blake3` feature)"
+                    .to_string(),
+            ))
+        }
+    }
+}
+
+// Architectures a Mach-O, ELF or PE binary is built for, named like `std::env::consts::ARCH`.
+// Empty when the format isn't recognized (a universal Mach-O lists every slice).
+fn binary_architectures(path: &Path) -> Result<Vec<&'static str>, AppError> {
+    let mut header = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(4096).read_to_end(&mut header))
+        .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+    let u16_le = |at: usize| {
+        header
+            .get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u16_be = |at: usize| {
+        header
+            .get(at..at + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+    let u32_le = |at: usize| {
+        header
+            .get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let u32_be = |at: usize| {
+        header
+            .get(at..at + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let mach_cpu = |cpu_type: u32| match cpu_type {
+        0x0100_0007 => Some("x86_64"),
+        0x0100_000C => Some("aarch64"),
+        0x0000_0007 => Some("x86"),
+        0x0000_000C => Some("arm"),
+        _ => None,
+    };
+
+    let arches = if header.starts_with(b"\x7fELF") {
+        let machine = if header.get(5) == Some(&2) {
+            u16_be(18)
+        } else {
+            u16_le(18)
+        };
+        let arch = match machine {
+            Some(0x3E) => Some("x86_64"),
+            Some(0xB7) => Some("aarch64"),
+            Some(0x03) => Some("x86"),
+            Some(0x28) => Some("arm"),
+            Some(0xF3) => Some("riscv64"),
+            _ => None,
+        };
+        arch.into_iter().collect()
+    } else if header.starts_with(&[0xCA, 0xFE, 0xBA, 0xBE]) {
+        let count = u32_be(4).unwrap_or(0) as usize;
+        (0..count.min(16))
+            .filter_map(|slice| u32_be(8 + slice * 20).and_then(mach_cpu))
+            .collect()
+    } else if header.starts_with(&[0xCF, 0xFA, 0xED, 0xFE])
+        || header.starts_with(&[0xCE, 0xFA, 0xED, 0xFE])
+    {
+        u32_le(4).and_then(mach_cpu).into_iter().collect()
+    } else if header.starts_with(b"MZ") {
+        let pe = u32_le(0x3C).unwrap_or(0) as usize;
+        let machine = match header.get(pe..pe + 4) {
+            Some(b"PE\0\0") => u16_le(pe + 4),
+            _ => None,
+        };
+        let arch = match machine {
+            Some(0x8664) => Some("x86_64"),
+            Some(0xAA64) => Some("aarch64"),
+            Some(0x014C) => Some("x86"),
+            Some(0x01C4) => Some("arm"),
+            _ => None,
+        };
+        arch.into_iter().collect()
+    } else {
+        Vec::new()
+    };
+    Ok(arches)
+}
+
+// An x86_64 sidecar on Apple Silicon runs under Rosetta and crashes in confusing ways, so say
+// so up front. Unrecognized formats are let through.
+fn check_sidecar_architecture(path: &Path) -> Result<(), AppError> {
+    let arches = binary_architectures(path)?;
+    let host = std::env::consts::ARCH;
+    // 64-bit x86 hosts still run 32-bit builds natively.
+    let compatible = arches.contains(&host) || (host == "x86_64" && arches.contains(&"x86"));
+    if arches.is_empty() || compatible {
+        return Ok(());
     }
+    Err(AppError::SidecarUnavailable(format!(
+        "Sidecar architecture mismatch: {} is built for {}, but this machine is {}",
+        path.display(),
+        arches.join(", "),
+        host
+    )))
+}
 
-    (battery_pct, on_ac)
+// What the installed binary looks like against its checksum and signature; the digests are
+// hex-encoded.
+#[derive(Serialize)]
+struct SidecarVerification {
+    name: String,
+    path: String,
+    algorithm: HashAlgorithm,
+    expected: String,
+    actual: String,
+    checksum_ok: bool,
+    signed: bool,
+    signature_error: Option<AppError>,
 }
 
-fn read_cpu_temp(components: &Components) -> Option<f32> {
-    components
-        .iter()
-        .find(|component| component.label().to_lowercase().contains("cpu"))
-        .map(|component| component.temperature())
+fn inspect_sidecar_binary(
+    app_handle: &tauri::AppHandle,
+    name: &str,
+) -> Result<SidecarVerification, AppError> {
+    let (sidecar_path, checksum_path) = resolve_sidecar_paths(app_handle, name)?;
+    inspect_sidecar_at(app_handle, name, &sidecar_path, &checksum_path)
 }
 
-async fn start_resource_monitor(app: tauri::AppHandle, state: Arc<Mutex<SidecarState>>) {
-    let mut guard = state.lock().await;
-    if guard.resource_task_running {
-        return;
+fn inspect_sidecar_at(
+    app_handle: &tauri::AppHandle,
+    name: &str,
+    sidecar_path: &Path,
+    checksum_path: &Path,
+) -> Result<SidecarVerification, AppError> {
+    if !sidecar_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Sidecar executable not found: {:?}",
+            sidecar_path
+        )));
+    }
+    if !checksum_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Sidecar checksum not found: {:?}",
+            checksum_path
+        )));
     }
-    guard.resource_task_running = true;
-    drop(guard);
-    let data_root = data_root(&app);
 
-    tauri::async_runtime::spawn(async move {
-        let mut system = System::new_all();
-        let mut components = Components::new_with_refreshed_list();
-        loop {
-            system.refresh_cpu();
-            components.refresh();
-
-            let cpu_usage = system.global_cpu_info().cpu_usage();
-            let cpu_temp = read_cpu_temp(&components);
-            let (battery_pct, on_ac_power) = read_battery_status();
-
-            let throttled = cpu_usage > 70.0
-                || cpu_temp.map(|temp| temp > 85.0).unwrap_or(false)
-                || battery_pct
-                    .zip(on_ac_power)
-                    .map(|(pct, ac)| pct < 30.0 && !ac)
-                    .unwrap_or(false);
-
-            let payload = serde_json::json!({
-                "timestamp": chrono::Utc::now().timestamp(),
-                "cpu_usage_pct": cpu_usage,
-                "cpu_temp_c": cpu_temp,
-                "battery_pct": battery_pct,
-                "on_ac_power": on_ac_power,
-                "throttled": throttled,
-            });
+    let algo = HashAlgorithm::from_path(checksum_path).ok_or_else(|| {
+        AppError::IntegrityFailed(format!("Unknown checksum algorithm: {:?}", checksum_path))
+    })?;
+    let file_name = sidecar_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let expected = read_checksum(checksum_path, &file_name)?;
+    let total_bytes = fs::metadata(sidecar_path)
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+    let mut bytes_hashed = 0u64;
+    let actual = hash_file_with_progress(sidecar_path, algo, |chunk_len| {
+        bytes_hashed += chunk_len;
+        let _ = app_handle.emit_all(
+            "sidecar_verify_progress",
+            serde_json::json!({
+                "name": name,
+                "bytes_hashed": bytes_hashed,
+                "total_bytes": total_bytes,
+            }),
+        );
+    })?;
 
-            let target = data_root.join("mesh").join("resource_state.json");
-            let _ = write_json_atomic(&target, &payload.to_string());
-            let _ = app.emit_all("resource_state", payload);
+    // The checksum only catches corruption; the signature is what catches tampering.
+    let sig_path = sidecar_path.with_file_name(format!(
+        "{}.sig",
+        sidecar_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+    ));
+    let signed = sig_path.exists();
+    let signature_error = if signed {
+        embedded_signing_key()
+            .and_then(|pubkey| verify_signature(sidecar_path, &sig_path, &pubkey))
+            .err()
+    } else {
+        None
+    };
+    Ok(SidecarVerification {
+        name: name.to_string(),
+        path: sidecar_path.to_string_lossy().to_string(),
+        algorithm: algo,
+        checksum_ok: actual == expected,
+        expected: hex::encode(expected),
+        actual: hex::encode(actual),
+        signed,
+        signature_error,
+    })
+}
 
-            tokio::time::sleep(Duration::from_secs(5)).await;
-        }
-    });
+fn verify_sidecar_integrity(app_handle: &tauri::AppHandle, name: &str) -> Result<(), AppError> {
+    let report = inspect_sidecar_binary(app_handle, name)?;
+    if !report.checksum_ok {
+        return Err(AppError::IntegrityFailed(format!(
+            "Sidecar checksum mismatch ({})",
+            report.algorithm.extension()
+        )));
+    }
+    match report.signature_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
 }
 
-fn main() {
-    tauri::Builder::default()
-        .setup(|app| {
-            let sidecar_state = Arc::new(Mutex::new(SidecarState::new()));
-            app.manage(sidecar_state.clone());
+// Re-checks the installed binary without touching a running process; a mismatch is reported in
+// the result rather than as an error.
+#[tauri::command]
+async fn verify_sidecar(
+    app: tauri::AppHandle,
+    name: Option<String>,
+) -> Result<SidecarVerification, AppError> {
+    let name = sidecar_name(name)?;
+    tauri::async_runtime::spawn_blocking(move || inspect_sidecar_binary(&app, &name))
+        .await
+        .map_err(|e| AppError::Io(format!("Integrity verification task failed: {e}")))?
+}
 
-            let app_handle = app.handle();
-            tauri::async_runtime::spawn(start_resource_monitor(
-                app_handle,
-                sidecar_state.clone(),
-            ));
-            
-            // Cleanup on app exit
-            app.listen_global("tauri://close-requested", move |_event| {
-                let state = sidecar_state.clone();
-                tauri::async_runtime::spawn(async move {
-                    let mut state = state.lock().await;
-                    if let Some(process) = state.process.take() {
-                        println!("[Tauri] Stopping sidecar process...");
-                        let _ = process.kill();
-                        println!("[Tauri] Sidecar stopped");
-                    }
-                });
+#[derive(Serialize)]
+struct InstalledSidecar {
+    name: String,
+    file_name: String,
+    path: String,
+    size_bytes: u64,
+    checksum_present: bool,
+    algorithm: Option<HashAlgorithm>,
+    // Checksum and (when signed) signature both pass; false with `error` set otherwise.
+    verified: bool,
+    error: Option<String>,
+}
+
+// The directory bundled sidecars are installed in: `PROJECT_DAWN_SIDECAR_DIR`, else the
+// `sidecar/` resource directory.
+fn sidecar_directory(app_handle: &tauri::AppHandle) -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("PROJECT_DAWN_SIDECAR_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    Some(app_handle.path_resolver().resource_dir()?.join("sidecar"))
+}
+
+fn is_executable_file(path: &Path, meta: &fs::Metadata) -> bool {
+    if !meta.is_file() {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = path;
+        meta.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+    }
+}
+
+fn list_sidecars_blocking(
+    app_handle: &tauri::AppHandle,
+) -> Result<Vec<InstalledSidecar>, AppError> {
+    let Some(dir) = sidecar_directory(app_handle) else {
+        return Err(AppError::NotFound(
+            "Resource directory unavailable".to_string(),
+        ));
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(AppError::io(
+                format!("Failed to list {}", dir.display()),
+                err,
+            ))
+        }
+    };
+    let mut sidecars = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if !is_executable_file(&path, &meta) {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let name = if cfg!(windows) {
+            file_name.trim_end_matches(".exe").to_string()
+        } else {
+            file_name.clone()
+        };
+        let checksum_path = checksum_path_for(&path);
+        let checksum_present = checksum_path.exists();
+        let (verified, algorithm, error) = if checksum_present {
+            match inspect_sidecar_at(app_handle, &name, &path, &checksum_path) {
+                Ok(report) => {
+                    let error = if !report.checksum_ok {
+                        Some("Checksum mismatch".to_string())
+                    } else {
+                        report.signature_error.map(|err| err.to_string())
+                    };
+                    (error.is_none(), Some(report.algorithm), error)
+                }
+                Err(err) => (
+                    false,
+                    HashAlgorithm::from_path(&checksum_path),
+                    Some(err.to_string()),
+                ),
+            }
+        } else {
+            (false, None, Some("No checksum".to_string()))
+        };
+        sidecars.push(InstalledSidecar {
+            name,
+            file_name,
+            path: path.to_string_lossy().to_string(),
+            size_bytes: meta.len(),
+            checksum_present,
+            algorithm,
+            verified,
+            error,
+        });
+    }
+    sidecars.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(sidecars)
+}
+
+// Everything executable in the sidecar directory, each hashed against its checksum. Nothing
+// running is touched.
+#[tauri::command]
+async fn list_sidecars(app: tauri::AppHandle) -> Result<Vec<InstalledSidecar>, AppError> {
+    tauri::async_runtime::spawn_blocking(move || list_sidecars_blocking(&app))
+        .await
+        .map_err(|e| AppError::Io(format!("Sidecar listing task failed: {e}")))?
+}
+
+// Hashing a large sidecar takes long enough to stall the UI, so keep it off the async workers.
+async fn verify_sidecar_integrity_async(
+    app_handle: &tauri::AppHandle,
+    name: &str,
+) -> Result<(), AppError> {
+    let app_handle = app_handle.clone();
+    let name = name.to_string();
+    tauri::async_runtime::spawn_blocking(move || verify_sidecar_integrity(&app_handle, &name))
+        .await
+        .map_err(|e| AppError::Io(format!("Integrity verification task failed: {e}")))?
+}
+
+#[tauri::command]
+fn app_version(app: tauri::AppHandle) -> Result<String, AppError> {
+    Ok(app.package_info().version.to_string())
+}
+
+#[derive(Serialize)]
+struct SidecarVersion {
+    name: String,
+    // What the running sidecar answers on `/version`; None when stopped or unsupported.
+    reported_version: Option<String>,
+    // From `<binary>.version`, written next to the binary when the sidecar is built.
+    bundled_version: Option<String>,
+    // Digest of the bundled binary (not of an external sidecar), to match against a release.
+    algorithm: Option<HashAlgorithm>,
+    checksum: Option<String>,
+}
+
+async fn read_http_response(host: &str, port: u16, request: &str) -> Option<String> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::TcpStream::connect((host, port)).await.ok()?;
+    stream.write_all(request.as_bytes()).await.ok()?;
+    let mut response = Vec::new();
+    stream
+        .take(64 * 1024)
+        .read_to_end(&mut response)
+        .await
+        .ok()?;
+    Some(String::from_utf8_lossy(&response).into_owned())
+}
+
+// Accepts `{"version": "..."}` or a bare version string.
+async fn fetch_sidecar_version(metrics_port: u16) -> Option<String> {
+    let request = format!(
+        "GET /version HTTP/1.1\r\nHost: 127.0.0.1:{metrics_port}\r\nConnection: close\r\n\r\n"
+    );
+    let response = tokio::time::timeout(
+        HEALTH_CHECK_TIMEOUT,
+        read_http_response("127.0.0.1", metrics_port, &request),
+    )
+    .await
+    .ok()??;
+    let (head, body) = response.split_once("\r\n\r\n")?;
+    if head.split_whitespace().nth(1) != Some("200") {
+        return None;
+    }
+    let body = body.trim();
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(value) => value.get("version")?.as_str().map(str::to_string),
+        Err(_) => Some(body.to_string()).filter(|version| !version.is_empty()),
+    }
+}
+
+fn bundled_sidecar_version(
+    app: &tauri::AppHandle,
+    name: &str,
+) -> (Option<String>, Option<(HashAlgorithm, String)>) {
+    let Ok((sidecar_path, checksum_path)) = resolve_sidecar_paths(app, name) else {
+        return (None, None);
+    };
+    let version_path = sidecar_path.with_file_name(format!(
+        "{}.version",
+        sidecar_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+    ));
+    let version = fs::read_to_string(version_path)
+        .ok()
+        .map(|version| version.trim().to_string())
+        .filter(|version| !version.is_empty());
+    // Same algorithm as the shipped checksum so the two can be compared directly.
+    let algo = HashAlgorithm::from_path(&checksum_path)
+        .filter(|algo| algo.is_supported())
+        .unwrap_or(HashAlgorithm::Sha256);
+    let checksum = hash_file(&sidecar_path, algo)
+        .ok()
+        .map(|digest| (algo, hex::encode(digest)));
+    (version, checksum)
+}
+
+#[tauri::command]
+async fn sidecar_version(
+    registry: State<'_, SidecarRegistry>,
+    app: tauri::AppHandle,
+    name: Option<String>,
+) -> Result<SidecarVersion, AppError> {
+    let name = sidecar_name(name)?;
+    let metrics_port = match registry.get(&name) {
+        Some(state) => {
+            let guard = state.lock().await;
+            (guard.mode() != SidecarMode::Stopped).then_some(guard.metrics_port)
+        }
+        None => None,
+    };
+    let reported_version = match metrics_port {
+        Some(port) => fetch_sidecar_version(port).await,
+        None => None,
+    };
+    let task_name = name.clone();
+    let (bundled_version, checksum) =
+        tauri::async_runtime::spawn_blocking(move || bundled_sidecar_version(&app, &task_name))
+            .await
+            .map_err(|e| AppError::Io(format!("Sidecar version task failed: {e}")))?;
+    Ok(SidecarVersion {
+        name,
+        reported_version,
+        bundled_version,
+        algorithm: checksum.as_ref().map(|(algo, _)| *algo),
+        checksum: checksum.map(|(_, digest)| digest),
+    })
+}
+
+// Published next to sidecar builds:
+// `{ "version": "…", "platforms": { "linux-x86_64": { "url": "…", "sha256": "…" } } }`.
+#[derive(Deserialize)]
+struct UpdateManifest {
+    version: String,
+    platforms: HashMap<String, UpdateArtifact>,
+}
+
+#[derive(Deserialize)]
+struct UpdateArtifact {
+    url: String,
+    sha256: Option<String>,
+    sha512: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SidecarUpdateCheck {
+    // False when there was nothing to compare against (no URL configured, network down, bad
+    // manifest); `error` says why. Not being able to check isn't treated as a failure.
+    checked: bool,
+    update_available: bool,
+    // The installed version, or its digest when it has no `.version` file.
+    current: Option<String>,
+    available: Option<String>,
+    url: Option<String>,
+    error: Option<String>,
+}
+
+impl SidecarUpdateCheck {
+    fn unchecked(current: Option<String>, error: String) -> Self {
+        warn!("Could not check for a sidecar update: {}", error);
+        Self {
+            checked: false,
+            update_available: false,
+            current,
+            available: None,
+            url: None,
+            error: Some(error),
+        }
+    }
+}
+
+async fn fetch_update_manifest(url: &str) -> Result<UpdateManifest, String> {
+    let client = reqwest::Client::builder()
+        .timeout(UPDATE_CHECK_TIMEOUT)
+        .build()
+        .map_err(|e| format!("HTTP client unavailable: {e}"))?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid update manifest at {url}: {e}"))
+}
+
+// The manifest entry for this platform, keyed like `linux-x86_64` or `macos-aarch64`.
+fn update_platform_key() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+// Compares the installed default sidecar against the update manifest. The digest decides when
+// the manifest lists one in the installed checksum's algorithm; otherwise the version strings.
+#[tauri::command]
+async fn check_sidecar_update(app: tauri::AppHandle) -> Result<SidecarUpdateCheck, AppError> {
+    let task_app = app.clone();
+    let (version, checksum) = tauri::async_runtime::spawn_blocking(move || {
+        bundled_sidecar_version(&task_app, DEFAULT_SIDECAR_NAME)
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Sidecar version task failed: {e}")))?;
+    let current = version
+        .clone()
+        .or_else(|| checksum.as_ref().map(|(_, digest)| digest.clone()));
+
+    let Some(manifest_url) = update_manifest_url(&app) else {
+        return Ok(SidecarUpdateCheck::unchecked(
+            current,
+            "No update manifest URL configured".to_string(),
+        ));
+    };
+    let manifest = match fetch_update_manifest(&manifest_url).await {
+        Ok(manifest) => manifest,
+        Err(err) => return Ok(SidecarUpdateCheck::unchecked(current, err)),
+    };
+    let platform = update_platform_key();
+    let Some(artifact) = manifest.platforms.get(&platform) else {
+        return Ok(SidecarUpdateCheck::unchecked(
+            current,
+            format!("The update manifest has no build for {platform}"),
+        ));
+    };
+
+    let published_digest = checksum.as_ref().and_then(|(algo, _)| match algo {
+        HashAlgorithm::Sha256 => artifact.sha256.as_deref(),
+        HashAlgorithm::Sha512 => artifact.sha512.as_deref(),
+        HashAlgorithm::Blake3 => None,
+    });
+    let update_available = match (published_digest, &checksum) {
+        (Some(published), Some((_, installed))) => !published.eq_ignore_ascii_case(installed),
+        _ => version.as_deref() != Some(manifest.version.as_str()),
+    };
+    Ok(SidecarUpdateCheck {
+        checked: true,
+        update_available,
+        current,
+        available: Some(manifest.version),
+        url: Some(artifact.url.clone()),
+        error: None,
+    })
+}
+
+fn embedded_signing_key() -> Result<[u8; 32], AppError> {
+    let encoded = SIDECAR_SIGNING_PUBKEY.ok_or_else(|| {
+        AppError::IntegrityFailed(
+            "Sidecar is signed but this build has no signing key embedded".to_string(),
+        )
+    })?;
+    <[u8; 32]>::from_hex(encoded.trim())
+        .map_err(|e| AppError::IntegrityFailed(format!("Invalid embedded signing key: {e}")))
+}
+
+fn read_signature(sig_path: &Path) -> Result<Signature, AppError> {
+    let raw = fs::read(sig_path).map_err(|e| {
+        AppError::io(
+            format!("Failed to read signature {}", sig_path.display()),
+            e,
+        )
+    })?;
+    // Accept either the raw 64-byte signature or its hex encoding.
+    let bytes = if raw.len() == 64 {
+        raw
+    } else {
+        let text = String::from_utf8_lossy(&raw);
+        Vec::from_hex(text.trim())
+            .map_err(|e| AppError::IntegrityFailed(format!("Invalid signature format: {e}")))?
+    };
+    Signature::from_slice(&bytes)
+        .map_err(|e| AppError::IntegrityFailed(format!("Invalid signature: {e}")))
+}
+
+fn verify_signature(path: &Path, sig_path: &Path, pubkey: &[u8; 32]) -> Result<(), AppError> {
+    let key = VerifyingKey::from_bytes(pubkey)
+        .map_err(|e| AppError::IntegrityFailed(format!("Invalid signing key: {e}")))?;
+    let signature = read_signature(sig_path)?;
+    let message = fs::read(path)
+        .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+    key.verify_strict(&message, &signature).map_err(|_| {
+        AppError::IntegrityFailed(format!(
+            "Signature verification failed for {}",
+            path.display()
+        ))
+    })
+}
+
+// Clears a `*_task_running` flag when a monitor task ends, including by panic, so the next
+// start can re-arm it.
+struct TaskFlagGuard(Arc<AtomicBool>);
+
+impl TaskFlagGuard {
+    // Sets the flag and returns the guard to move into the task; None when the flag was already
+    // set, i.e. the task is still running.
+    fn claim(flag: &Arc<AtomicBool>) -> Option<Self> {
+        flag.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| Self(flag.clone()))
+    }
+}
+
+impl Drop for TaskFlagGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+async fn start_health_monitor<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    state: Arc<Mutex<SidecarState>>,
+) {
+    let guard = state.lock().await;
+    let Some(running) = TaskFlagGuard::claim(&guard.health_task_running) else {
+        return;
+    };
+    let span = tracing::info_span!("health_check", sidecar = %guard.name);
+    drop(guard);
+
+    let task = async move {
+        loop {
+            let root = data_root(&app);
+            let config = off_runtime(move || load_health_config(&root))
+                .await
+                .unwrap_or_default();
+            tokio::time::sleep(config.interval()).await;
+            let mut guard = state.lock().await;
+            // Stand down once the sidecar has been stopped; the next start re-arms us. The flag
+            // is cleared before the lock is released, so a start that takes it next sees us
+            // gone and arms a new monitor.
+            if guard.stop_requested && guard.process.is_none() && !guard.external {
+                drop(running);
+                return;
+            }
+            // The port can change across restarts, so re-read it every cycle.
+            let port = guard.port;
+            drop(guard);
+            let started = Instant::now();
+            let probe = probe_sidecar(port, config.timeout()).await;
+            let mut guard = state.lock().await;
+            let previous_failures = guard.health.consecutive_failures;
+            guard.health.record(&probe, started.elapsed());
+            // Log the first failure, then at most once a minute until it recovers.
+            if !probe.is_healthy() {
+                let due = guard
+                    .health_last_logged
+                    .map_or(true, |at| at.elapsed() >= HEALTH_LOG_INTERVAL);
+                if previous_failures == 0 || due {
+                    warn!(
+                        "Sidecar health check failed on port {} ({} in a row): {:?}",
+                        port, guard.health.consecutive_failures, probe
+                    );
+                    guard.health_last_logged = Some(Instant::now());
+                }
+            } else if previous_failures > 0 {
+                info!(
+                    "Sidecar healthy again on port {} after {} failed checks",
+                    port, previous_failures
+                );
+                guard.health_last_logged = None;
+            }
+
+            // Edge-triggered: one event when the threshold is crossed, one on recovery.
+            let unhealthy = guard.health.consecutive_failures >= config.failure_threshold.max(1);
+            let event = match (guard.reported_unhealthy, unhealthy, probe.is_healthy()) {
+                (false, true, _) => Some("sidecar_unhealthy"),
+                (true, _, true) => Some("sidecar_healthy"),
+                _ => None,
+            };
+            if let Some(event) = event {
+                guard.reported_unhealthy = event == "sidecar_unhealthy";
+                let health = guard.health.clone();
+                drop(guard);
+                let _ = app.emit_all(event, health);
+            }
+        }
+    };
+    tauri::async_runtime::spawn(task.instrument(span));
+}
+
+// Polls readiness quickly after a launch and emits `sidecar_ready` once; exits early when the
+// launch it was started for is replaced or stopped.
+#[tracing::instrument(name = "sidecar_readiness", skip(app, state))]
+async fn watch_readiness(app: tauri::AppHandle, state: Arc<Mutex<SidecarState>>, launch_id: u64) {
+    loop {
+        tokio::time::sleep(READY_POLL_INTERVAL).await;
+        let guard = state.lock().await;
+        if guard.launch_id != launch_id || (guard.process.is_none() && !guard.external) {
+            return;
+        }
+        let metrics_port = guard.metrics_port;
+        drop(guard);
+
+        if !probe_ready(metrics_port).await.is_healthy() {
+            continue;
+        }
+        let mut guard = state.lock().await;
+        if guard.launch_id != launch_id {
+            return;
+        }
+        guard.ready = true;
+        guard.ready_notify.notify_waiters();
+        let port = guard.port;
+        let name = guard.name.clone();
+        drop(guard);
+        info!("Sidecar {} ready", name);
+        let _ = app.emit_all(
+            "sidecar_ready",
+            serde_json::json!({ "name": name, "port": port, "metrics_port": metrics_port }),
+        );
+        return;
+    }
+}
+
+async fn await_sidecar_ready(
+    state: &Arc<Mutex<SidecarState>>,
+    timeout: Duration,
+) -> Result<(), AppError> {
+    let ready_notify = state.lock().await.ready_notify.clone();
+    // Register before checking the flag so a transition in between isn't missed.
+    let notified = ready_notify.notified();
+    tokio::pin!(notified);
+    notified.as_mut().enable();
+    if state.lock().await.ready {
+        return Ok(());
+    }
+    tokio::time::timeout(timeout, notified).await.map_err(|_| {
+        AppError::SidecarUnavailable(format!("Sidecar not ready after {}ms", timeout.as_millis()))
+    })
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum SidecarMode {
+    Stopped,
+    // Spawned but /health/ready hasn't answered yet (models loading).
+    Loading,
+    Running,
+    External,
+}
+
+// A name that was never started reports `stopped`.
+#[tauri::command]
+async fn sidecar_status(
+    registry: State<'_, SidecarRegistry>,
+    name: Option<String>,
+) -> Result<SidecarMode, AppError> {
+    let Some(state) = registry.get(&sidecar_name(name)?) else {
+        return Ok(SidecarMode::Stopped);
+    };
+    Ok(state.lock().await.mode())
+}
+
+// The WebSocket port the sidecar is on (an attached external one included), or None while it's
+// stopped. It isn't always 8000: a busy port moves the sidecar elsewhere.
+#[tauri::command]
+async fn sidecar_port(
+    registry: State<'_, SidecarRegistry>,
+    name: Option<String>,
+) -> Result<Option<u16>, AppError> {
+    let Some(state) = registry.get(&sidecar_name(name)?) else {
+        return Ok(None);
+    };
+    let guard = state.lock().await;
+    Ok((guard.mode() != SidecarMode::Stopped).then_some(guard.port))
+}
+
+// Seconds since the current process was spawned (or attached), reset by automatic restarts;
+// None while stopped.
+#[tauri::command]
+async fn sidecar_uptime_secs(
+    registry: State<'_, SidecarRegistry>,
+    name: Option<String>,
+) -> Result<Option<u64>, AppError> {
+    let Some(state) = registry.get(&sidecar_name(name)?) else {
+        return Ok(None);
+    };
+    let uptime = state.lock().await.uptime_secs();
+    Ok(uptime)
+}
+
+// `PROJECT_DAWN_EXTERNAL_SIDECAR=1` makes start_sidecar attach to an already-running server on
+// `PROJECT_DAWN_WS_PORT` (default 8000) instead of spawning the bundled one.
+fn external_sidecar_port() -> Option<u16> {
+    let enabled = std::env::var("PROJECT_DAWN_EXTERNAL_SIDECAR")
+        .map(|value| matches!(value.as_str(), "1" | "true"))
+        .unwrap_or(false);
+    if !enabled {
+        return None;
+    }
+    Some(
+        std::env::var("PROJECT_DAWN_WS_PORT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_SIDECAR_PORT),
+    )
+}
+
+fn external_metrics_port() -> u16 {
+    std::env::var("PROJECT_DAWN_METRICS_PORT")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_METRICS_PORT)
+}
+
+async fn attach_external(
+    app: &tauri::AppHandle,
+    state: &Arc<Mutex<SidecarState>>,
+    port: u16,
+    metrics_port: u16,
+) -> Result<(), AppError> {
+    let mut guard = state.lock().await;
+    if guard.process.is_some() {
+        return Err(AppError::SidecarUnavailable(
+            "The bundled sidecar is running; stop it before attaching".to_string(),
+        ));
+    }
+    guard.external = true;
+    guard.port = port;
+    guard.metrics_port = metrics_port;
+    guard.stop_requested = false;
+    guard.health = HealthStatus {
+        socket_connected: guard.health.socket_connected,
+        ..HealthStatus::default()
+    };
+    let launch_id = guard.begin_launch();
+    drop(guard);
+    info!("Using external sidecar on port {}", port);
+    tauri::async_runtime::spawn(watch_readiness(app.clone(), state.clone(), launch_id));
+    start_health_monitor(app.clone(), state.clone()).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn attach_external_sidecar(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    app: tauri::AppHandle,
+    port: u16,
+    metrics_port: Option<u16>,
+) -> Result<bool, AppError> {
+    let metrics_port = metrics_port.unwrap_or_else(external_metrics_port);
+    attach_external(&app, state.inner(), port, metrics_port).await?;
+    Ok(true)
+}
+
+#[tauri::command]
+async fn sidecar_health_detail(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+) -> Result<HealthStatus, AppError> {
+    let guard = state.lock().await;
+    Ok(guard.health.clone())
+}
+
+fn pick_free_port(preferred: u16) -> Result<u16, AppError> {
+    if preferred != 0 && TcpListener::bind(("127.0.0.1", preferred)).is_ok() {
+        return Ok(preferred);
+    }
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .map_err(|e| AppError::SidecarUnavailable(format!("Failed to find a free port: {e}")))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| AppError::SidecarUnavailable(format!("Failed to read assigned port: {e}")))?
+        .port();
+    if preferred != 0 {
+        warn!(
+            "Port {} is in use, using {} for the sidecar",
+            preferred, port
+        );
+    }
+    Ok(port)
+}
+
+fn spawn_sidecar_process(
+    app: &tauri::AppHandle,
+    name: &str,
+    port: u16,
+    metrics_port: u16,
+    extra_env: &HashMap<String, String>,
+) -> Result<(Receiver<CommandEvent>, CommandChild), AppError> {
+    let data_root = data_root(app);
+    let mut env = extra_env.clone();
+    if !extra_env.is_empty() {
+        // Values may hold secrets, so only the names are logged.
+        let mut names: Vec<&str> = extra_env.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        info!("Extra sidecar environment: {}", names.join(", "));
+    }
+    env.insert(
+        "PROJECT_DAWN_DATA_ROOT".to_string(),
+        data_root.to_string_lossy().to_string(),
+    );
+    env.insert("PROJECT_DAWN_WS_PORT".to_string(), port.to_string());
+    env.insert(
+        "PROJECT_DAWN_METRICS_PORT".to_string(),
+        metrics_port.to_string(),
+    );
+    let command = match sidecar_path_override(name) {
+        Some(path) => {
+            warn!(
+                "PROJECT_DAWN_SIDECAR_PATH override active: spawning {} instead of the bundled sidecar",
+                path.display()
+            );
+            Command::new(path.to_string_lossy())
+        }
+        None => Command::new_sidecar(name).map_err(|e| {
+            AppError::SidecarUnavailable(format!("Failed to configure sidecar: {e}"))
+        })?,
+    };
+    command
+        .envs(env)
+        .spawn()
+        .map_err(|e| AppError::SidecarUnavailable(format!("Failed to start sidecar: {e}")))
+}
+
+// A sidecar left behind by a crashed run still holds the preferred port. If it completes the
+// WebSocket handshake we reuse it rather than start a second instance; if it accepts the
+// connection but never answers, it's wedged and we refuse to guess. Anything else on the port
+// is unrelated and pick_free_port moves us elsewhere.
+async fn find_running_sidecar(port: u16) -> Result<bool, AppError> {
+    if TcpListener::bind(("127.0.0.1", port)).is_ok() {
+        return Ok(false);
+    }
+    match probe_sidecar(port, HEALTH_CHECK_TIMEOUT).await {
+        HealthProbe::Healthy => Ok(true),
+        HealthProbe::Timeout => Err(AppError::SidecarUnavailable(format!(
+            "Port {} is held by a process that doesn't respond; stop it and try again",
+            port
+        ))),
+        HealthProbe::Unreachable(_) | HealthProbe::BadResponse(_) => Ok(false),
+    }
+}
+
+fn pick_sidecar_ports(state: &SidecarState) -> Result<(u16, u16), AppError> {
+    Ok((
+        pick_free_port(state.preferred_port)?,
+        pick_free_port(state.preferred_metrics_port)?,
+    ))
+}
+
+fn restart_backoff(attempt: u32) -> Duration {
+    Duration::from_secs((1u64 << attempt.min(5)).min(MAX_RESTART_BACKOFF_SECS))
+}
+
+// How the last sidecar process ended; `code`/`signal` are both None if the event stream
+// closed without a termination event.
+#[derive(Serialize, Clone, Debug)]
+struct SidecarExit {
+    name: String,
+    code: Option<i32>,
+    signal: Option<i32>,
+    stderr_tail: Vec<String>,
+    at: i64,
+}
+
+#[tauri::command]
+async fn last_sidecar_exit(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+) -> Result<Option<SidecarExit>, AppError> {
+    Ok(state.lock().await.last_exit.clone())
+}
+
+// What one sidecar process has reported on its event stream.
+#[derive(Default)]
+struct SidecarRun {
+    stderr_tail: VecDeque<String>,
+    exit_status: (Option<i32>, Option<i32>),
+    terminated: bool,
+}
+
+impl SidecarRun {
+    // Records one event, handing each line for the sidecar log to `log(stream, line)`. Returns
+    // false once the process has terminated.
+    fn handle_event(&mut self, event: CommandEvent, mut log: impl FnMut(&str, &str)) -> bool {
+        match event {
+            CommandEvent::Stdout(line) => {
+                info!(target: "sidecar", "{}", line);
+                log("stdout", &line);
+            }
+            CommandEvent::Stderr(line) => {
+                info!(target: "sidecar", "{}", line);
+                log("stderr", &line);
+                if self.stderr_tail.len() == STDERR_TAIL_LINES {
+                    self.stderr_tail.pop_front();
+                }
+                self.stderr_tail.push_back(line);
+            }
+            CommandEvent::Error(err) => {
+                error!(target: "sidecar", "error: {}", err);
+                log("error", &err);
+            }
+            CommandEvent::Terminated(payload) => {
+                let message = format!(
+                    "terminated (code: {:?}, signal: {:?})",
+                    payload.code, payload.signal
+                );
+                warn!(target: "sidecar", "{}", message);
+                log("event", &message);
+                self.exit_status = (payload.code, payload.signal);
+                self.terminated = true;
+                return false;
+            }
+            // CommandEvent is non-exhaustive; whatever a newer Tauri adds still gets logged.
+            other => {
+                let message = format!("unhandled event: {:?}", other);
+                warn!(target: "sidecar", "{}", message);
+                log("event", &message);
+            }
+        }
+        true
+    }
+
+    // Called when the event stream ends; notes a stream that closed without a status.
+    fn finish(&self, mut log: impl FnMut(&str, &str)) {
+        if !self.terminated {
+            let message = "event stream closed without a termination status";
+            warn!(target: "sidecar", "{}", message);
+            log("event", message);
+        }
+    }
+}
+
+#[tracing::instrument(name = "sidecar", skip(app, state, rx))]
+async fn supervise_sidecar(
+    app: tauri::AppHandle,
+    state: Arc<Mutex<SidecarState>>,
+    mut rx: Receiver<CommandEvent>,
+    mut pid: u32,
+) {
+    let mut started_at = Instant::now();
+    loop {
+        let mut run = SidecarRun::default();
+        let log = |stream: &str, line: &str| log_sidecar_line(&app, stream, line);
+        while let Some(event) = rx.recv().await {
+            if !run.handle_event(event, log) {
+                break;
+            }
+        }
+        run.finish(log);
+
+        let name = state.lock().await.name.clone();
+        let exit = SidecarExit {
+            name,
+            code: run.exit_status.0,
+            signal: run.exit_status.1,
+            stderr_tail: run.stderr_tail.into_iter().collect(),
+            at: chrono::Utc::now().timestamp(),
+        };
+        let _ = app.emit_all("sidecar_exited", &exit);
+
+        let mut guard = state.lock().await;
+        guard.last_exit = Some(exit);
+        guard.exit_notify.notify_waiters();
+        // Only the supervisor of the current process may clear it; a stop or a
+        // fresh start_sidecar call has already replaced or removed our handle.
+        if guard.process.as_ref().map(|child| child.pid()) != Some(pid) {
+            return;
+        }
+        guard.process = None;
+        guard.ready = false;
+        guard.started_at = None;
+        if started_at.elapsed() >= Duration::from_secs(STABLE_RUN_SECS) {
+            guard.restart_attempts = 0;
+        }
+        drop(guard);
+
+        rx = loop {
+            if ensure_not_in_maintenance(&app).is_err() {
+                info!("Not restarting the sidecar during maintenance");
+                return;
+            }
+            let mut guard = state.lock().await;
+            if guard.stop_requested {
+                return;
+            }
+            if guard.restart_attempts >= guard.max_restarts {
+                error!(
+                    "Sidecar exited {} times, giving up on automatic restart",
+                    guard.restart_attempts
+                );
+                return;
+            }
+            let attempt = guard.restart_attempts;
+            guard.restart_attempts += 1;
+            drop(guard);
+
+            let delay = restart_backoff(attempt);
+            warn!("Restarting sidecar in {}s", delay.as_secs());
+            tokio::time::sleep(delay).await;
+
+            let name = state.lock().await.name.clone();
+            if ensure_not_in_maintenance(&app).is_err() {
+                return;
+            }
+            if let Err(err) = verify_sidecar_integrity_async(&app, &name).await {
+                error!("Sidecar restart failed: {}", err);
+                continue;
+            }
+            let mut guard = state.lock().await;
+            if guard.stop_requested
+                || guard.process.is_some()
+                || guard.external
+                || guard.updating.load(Ordering::SeqCst)
+            {
+                return;
+            }
+            let extra_env = guard.extra_env.clone();
+            let spawned = pick_sidecar_ports(&guard).and_then(|(port, metrics_port)| {
+                let spawned = spawn_sidecar_process(&app, &name, port, metrics_port, &extra_env)?;
+                Ok((port, metrics_port, spawned))
+            });
+            match spawned {
+                Ok((port, metrics_port, (next_rx, child))) => {
+                    pid = child.pid();
+                    guard.process = Some(child);
+                    guard.port = port;
+                    guard.metrics_port = metrics_port;
+                    let launch_id = guard.begin_launch();
+                    drop(guard);
+                    tauri::async_runtime::spawn(watch_readiness(
+                        app.clone(),
+                        state.clone(),
+                        launch_id,
+                    ));
+                    started_at = Instant::now();
+                    let _ = app.emit_all(
+                        "sidecar_restarted",
+                        serde_json::json!({
+                            "name": name,
+                            "attempt": attempt + 1,
+                            "delay_ms": delay.as_millis() as u64,
+                        }),
+                    );
+                    break next_rx;
+                }
+                Err(err) => error!("Sidecar restart failed: {}", err),
+            }
+        };
+    }
+}
+
+// Set by the shell itself; callers can't override them.
+const RESERVED_SIDECAR_ENV: [&str; 3] = [
+    "PROJECT_DAWN_DATA_ROOT",
+    "PROJECT_DAWN_WS_PORT",
+    "PROJECT_DAWN_METRICS_PORT",
+];
+
+fn validate_sidecar_env(vars: &HashMap<String, String>) -> Result<(), AppError> {
+    for key in vars.keys() {
+        if key.is_empty() || key.contains('=') || key.contains('\0') {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid environment variable name: {:?}",
+                key
+            )));
+        }
+        if RESERVED_SIDECAR_ENV.contains(&key.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "{} is managed by the shell",
+                key
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Where a start got to, for the error when it times out or is cancelled.
+#[derive(Clone, Copy, Debug)]
+enum StartPhase {
+    Verify,
+    Spawn,
+    Readiness,
+}
+
+impl StartPhase {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Verify => "verify",
+            Self::Spawn => "spawn",
+            Self::Readiness => "readiness",
+        }
+    }
+}
+
+fn set_start_phase(phase: &std::sync::Mutex<StartPhase>, value: StartPhase) {
+    match phase.lock() {
+        Ok(mut guard) => *guard = value,
+        Err(poisoned) => *poisoned.into_inner() = value,
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn launch_sidecar(
+    state: &Arc<Mutex<SidecarState>>,
+    app: &tauri::AppHandle,
+    extra_env: HashMap<String, String>,
+    phase: &std::sync::Mutex<StartPhase>,
+) -> Result<(), AppError> {
+    validate_sidecar_env(&extra_env)?;
+    let guard = state.lock().await;
+    if guard.process.is_some() || guard.external {
+        return Ok(());
+    }
+    if guard.updating.load(Ordering::SeqCst) {
+        return Err(AppError::SidecarUnavailable(
+            "A sidecar update is being installed".to_string(),
+        ));
+    }
+    let name = guard.name.clone();
+    let (preferred_port, preferred_metrics_port) =
+        (guard.preferred_port, guard.preferred_metrics_port);
+    drop(guard);
+
+    if name == DEFAULT_SIDECAR_NAME {
+        if let Some(port) = external_sidecar_port() {
+            return attach_external(app, state, port, external_metrics_port()).await;
+        }
+    }
+
+    if preferred_port != 0 && find_running_sidecar(preferred_port).await? {
+        info!(
+            "Reusing sidecar already listening on port {}",
+            preferred_port
+        );
+        return attach_external(app, state, preferred_port, preferred_metrics_port).await;
+    }
+
+    // Verify without holding the state lock so status queries stay responsive. An override
+    // binary is only checked when it comes with a checksum.
+    let (sidecar_path, checksum_path) = resolve_sidecar_paths(app, &name)?;
+    if sidecar_path_override(&name).is_some() && !checksum_path.exists() {
+        warn!(
+            "No checksum next to {}; starting it unverified",
+            sidecar_path.display()
+        );
+    } else {
+        verify_sidecar_integrity_async(app, &name).await?;
+    }
+    check_sidecar_architecture(&sidecar_path)?;
+
+    set_start_phase(phase, StartPhase::Spawn);
+    let mut guard = state.lock().await;
+    if guard.process.is_some() {
+        return Ok(());
+    }
+    let (port, metrics_port) = pick_sidecar_ports(&guard)?;
+    let (rx, child) = spawn_sidecar_process(app, &name, port, metrics_port, &extra_env)?;
+    let pid = child.pid();
+
+    guard.process = Some(child);
+    guard.port = port;
+    guard.metrics_port = metrics_port;
+    guard.stop_requested = false;
+    guard.restart_attempts = 0;
+    // Kept so automatic restarts come back with the same environment.
+    guard.extra_env = extra_env;
+    let launch_id = guard.begin_launch();
+    drop(guard);
+
+    tauri::async_runtime::spawn(supervise_sidecar(app.clone(), state.clone(), rx, pid));
+    tauri::async_runtime::spawn(watch_readiness(app.clone(), state.clone(), launch_id));
+
+    start_health_monitor(app.clone(), state.clone()).await;
+    // No-op while it's running; restarts it if the task died. There's one resource monitor
+    // for the machine and it lives on the default sidecar's state.
+    let default_state = app.state::<Arc<Mutex<SidecarState>>>().inner().clone();
+    start_resource_monitor(app.clone(), default_state).await;
+    Ok(())
+}
+
+// `PROJECT_DAWN_READONLY=1` runs the app as an observer of a node managed elsewhere: nothing is
+// spawned, stopped or written, while the getters and resource sampling keep working.
+struct ReadOnly(bool);
+
+fn readonly_from_env() -> bool {
+    std::env::var("PROJECT_DAWN_READONLY")
+        .map(|value| matches!(value.as_str(), "1" | "true"))
+        .unwrap_or(false)
+}
+
+fn ensure_writable(app: &tauri::AppHandle) -> Result<(), AppError> {
+    if app.state::<ReadOnly>().0 {
+        return Err(AppError::ReadOnly(
+            "The app is in read-only mode".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn is_readonly(readonly: State<'_, ReadOnly>) -> Result<bool, AppError> {
+    Ok(readonly.0)
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+struct MaintenanceState {
+    enabled: bool,
+    reason: Option<String>,
+    // Epoch seconds when it was turned on.
+    since: Option<i64>,
+}
+
+// While enabled (e.g. during an upgrade) no sidecar is started, restarted or auto-restarted.
+struct Maintenance(std::sync::Mutex<MaintenanceState>);
+
+impl Maintenance {
+    fn snapshot(&self) -> MaintenanceState {
+        match self.0.lock() {
+            Ok(state) => state.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+fn ensure_not_in_maintenance(app: &tauri::AppHandle) -> Result<(), AppError> {
+    let state = app.state::<Maintenance>().snapshot();
+    if !state.enabled {
+        return Ok(());
+    }
+    Err(AppError::Maintenance(match state.reason {
+        Some(reason) => format!("Maintenance mode is on: {}", reason),
+        None => "Maintenance mode is on".to_string(),
+    }))
+}
+
+// Turning it on stops every sidecar (gracefully, as on app close) after setting the flag, so
+// the supervisors see it and stay down.
+#[tauri::command]
+async fn set_maintenance_mode(
+    registry: State<'_, SidecarRegistry>,
+    maintenance: State<'_, Maintenance>,
+    app: tauri::AppHandle,
+    enabled: bool,
+    reason: Option<String>,
+) -> Result<MaintenanceState, AppError> {
+    ensure_writable(&app)?;
+    let state = MaintenanceState {
+        enabled,
+        reason: reason.filter(|_| enabled),
+        since: enabled.then(|| chrono::Utc::now().timestamp()),
+    };
+    match maintenance.0.lock() {
+        Ok(mut guard) => *guard = state.clone(),
+        Err(poisoned) => *poisoned.into_inner() = state.clone(),
+    }
+    if enabled {
+        info!(
+            "Maintenance mode on{}",
+            state
+                .reason
+                .as_ref()
+                .map(|reason| format!(": {}", reason))
+                .unwrap_or_default()
+        );
+        let timeout = Duration::from_millis(DEFAULT_SHUTDOWN_TIMEOUT_MS);
+        for sidecar in registry.all() {
+            shutdown_sidecar(&sidecar, timeout).await;
+        }
+    } else {
+        info!("Maintenance mode off");
+    }
+    let _ = app.emit_all("maintenance_changed", &state);
+    Ok(state)
+}
+
+#[tauri::command]
+fn maintenance_state(maintenance: State<'_, Maintenance>) -> Result<MaintenanceState, AppError> {
+    Ok(maintenance.snapshot())
+}
+
+// With `wait_ready_ms` the call only returns once the sidecar reports ready, or rejects with
+// `sidecar_unavailable` when it doesn't within that time (the process keeps running).
+// The whole start is bounded by `timeout_ms` (30s, or the readiness wait if that's longer) and
+// can be cancelled with a stop; either way a process it spawned is killed and the error names
+// the phase it was in.
+async fn start_and_maybe_wait(
+    state: &Arc<Mutex<SidecarState>>,
+    app: &tauri::AppHandle,
+    extra_env: HashMap<String, String>,
+    wait_ready_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+) -> Result<bool, AppError> {
+    ensure_writable(app)?;
+    ensure_not_in_maintenance(app)?;
+    let timeout_ms = timeout_ms
+        .unwrap_or_else(|| DEFAULT_START_TIMEOUT_MS.max(wait_ready_ms.unwrap_or_default()));
+    let guard = state.lock().await;
+    let (start_cancel, launch_id) = (guard.start_cancel.clone(), guard.launch_id);
+    drop(guard);
+    let cancelled = start_cancel.notified();
+    tokio::pin!(cancelled);
+    cancelled.as_mut().enable();
+
+    let phase = std::sync::Mutex::new(StartPhase::Verify);
+    let start = async {
+        launch_sidecar(state, app, extra_env, &phase).await?;
+        if let Some(wait_ready_ms) = wait_ready_ms {
+            set_start_phase(&phase, StartPhase::Readiness);
+            await_sidecar_ready(state, Duration::from_millis(wait_ready_ms)).await?;
+        }
+        Ok(true)
+    };
+    let outcome = tokio::select! {
+        result = tokio::time::timeout(Duration::from_millis(timeout_ms), start) => match result {
+            Ok(result) => return result,
+            Err(_) => format!("timed out after {}ms", timeout_ms),
+        },
+        _ = cancelled => "was cancelled".to_string(),
+    };
+    let phase = match phase.lock() {
+        Ok(guard) => *guard,
+        Err(poisoned) => *poisoned.into_inner(),
+    };
+
+    // Only a process this start spawned is ours to kill; an attached sidecar is left alone.
+    let mut guard = state.lock().await;
+    if guard.launch_id != launch_id && !guard.external {
+        if let Some(child) = guard.process.take() {
+            guard.stop_requested = true;
+            guard.ready = false;
+            guard.started_at = None;
+            let _ = child.kill();
+        }
+    }
+    drop(guard);
+    warn!("Sidecar start {} during {}", outcome, phase.as_str());
+    Err(AppError::SidecarUnavailable(format!(
+        "Sidecar start {} during {}",
+        outcome,
+        phase.as_str()
+    )))
+}
+
+// `name` picks a binary bundled under `sidecar/`; without it the default sidecar starts. Each
+// named sidecar gets its own ports, supervisor and health monitor.
+#[tauri::command]
+async fn start_sidecar(
+    registry: State<'_, SidecarRegistry>,
+    app: tauri::AppHandle,
+    name: Option<String>,
+    wait_ready_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+) -> Result<bool, AppError> {
+    let state = registry.get_or_insert(&sidecar_name(name)?);
+    start_and_maybe_wait(&state, &app, HashMap::new(), wait_ready_ms, timeout_ms).await
+}
+
+#[tauri::command]
+async fn start_sidecar_with_env(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    app: tauri::AppHandle,
+    vars: HashMap<String, String>,
+    wait_ready_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+) -> Result<bool, AppError> {
+    start_and_maybe_wait(state.inner(), &app, vars, wait_ready_ms, timeout_ms).await
+}
+
+#[tauri::command]
+async fn stop_sidecar(
+    registry: State<'_, SidecarRegistry>,
+    app: tauri::AppHandle,
+    name: Option<String>,
+) -> Result<bool, AppError> {
+    ensure_writable(&app)?;
+    let Some(state) = registry.get(&sidecar_name(name)?) else {
+        return Ok(false);
+    };
+    let mut guard = state.lock().await;
+    guard.stop_requested = true;
+    guard.ready = false;
+    guard.started_at = None;
+    guard.start_cancel.notify_waiters();
+    // Detaching from an external sidecar leaves the process alone.
+    if guard.external {
+        guard.external = false;
+        return Ok(true);
+    }
+    if let Some(child) = guard.process.take() {
+        let _ = child.kill();
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum StopOutcome {
+    NotRunning,
+    Clean,
+    Forced,
+}
+
+#[cfg(unix)]
+fn request_sidecar_shutdown(pid: u32) -> bool {
+    // SAFETY: kill(2) has no memory-safety preconditions; the pid is our own child.
+    unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) == 0 }
+}
+
+#[cfg(not(unix))]
+fn request_sidecar_shutdown(_pid: u32) -> bool {
+    // No SIGTERM equivalent for console processes; callers fall through to kill().
+    false
+}
+
+async fn shutdown_sidecar(state: &Arc<Mutex<SidecarState>>, timeout: Duration) -> StopOutcome {
+    let mut guard = state.lock().await;
+    guard.stop_requested = true;
+    guard.external = false;
+    guard.ready = false;
+    guard.started_at = None;
+    guard.start_cancel.notify_waiters();
+    let child = match guard.process.take() {
+        Some(child) => child,
+        None => return StopOutcome::NotRunning,
+    };
+    let exit_notify = guard.exit_notify.clone();
+    drop(guard);
+
+    // Register for the exit notification before signalling so a fast exit isn't missed.
+    let exited = exit_notify.notified();
+    tokio::pin!(exited);
+    exited.as_mut().enable();
+
+    if request_sidecar_shutdown(child.pid()) && tokio::time::timeout(timeout, exited).await.is_ok()
+    {
+        return StopOutcome::Clean;
+    }
+    let _ = child.kill();
+    StopOutcome::Forced
+}
+
+#[tauri::command]
+async fn stop_sidecar_graceful(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    app: tauri::AppHandle,
+    timeout_ms: u64,
+) -> Result<StopOutcome, AppError> {
+    ensure_writable(&app)?;
+    Ok(shutdown_sidecar(state.inner(), Duration::from_millis(timeout_ms)).await)
+}
+
+async fn wait_for_port_free(port: u16, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if TcpListener::bind(("127.0.0.1", port)).is_ok() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+// Stops gracefully, waits for the old ports to be released, then goes through the normal
+// launch path (verification included) with the same extra environment. Resolves once the
+// new process reports ready.
+#[tauri::command]
+async fn restart_sidecar(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    app: tauri::AppHandle,
+    timeout_ms: Option<u64>,
+) -> Result<bool, AppError> {
+    ensure_writable(&app)?;
+    ensure_not_in_maintenance(&app)?;
+    let state = state.inner();
+    let guard = state.lock().await;
+    if guard.external {
+        return Err(AppError::SidecarUnavailable(
+            "An external sidecar can't be restarted from the app".to_string(),
+        ));
+    }
+    let (port, metrics_port) = (guard.port, guard.metrics_port);
+    let extra_env = guard.extra_env.clone();
+    drop(guard);
+
+    let shutdown_timeout = Duration::from_millis(DEFAULT_SHUTDOWN_TIMEOUT_MS);
+    if shutdown_sidecar(state, shutdown_timeout).await != StopOutcome::NotRunning {
+        for port in [port, metrics_port] {
+            if !wait_for_port_free(port, shutdown_timeout).await {
+                warn!("Port {} still in use after stopping the sidecar", port);
+            }
+        }
+    }
+
+    let timeout_ms = timeout_ms.unwrap_or(DEFAULT_RESTART_READY_TIMEOUT_MS);
+    start_and_maybe_wait(state, &app, extra_env, Some(timeout_ms), None).await
+}
+
+// Files an update replaced, each with a copy of what was there before (None if nothing was).
+// Restoring walks them back in reverse, so a file touched twice ends up as it first was.
+struct UpdateRollback(Vec<(PathBuf, Option<PathBuf>)>);
+
+impl UpdateRollback {
+    fn save(&mut self, target: &Path) -> Result<(), AppError> {
+        let backup = if target.exists() {
+            let mut name = target.file_name().unwrap_or_default().to_os_string();
+            name.push(".rollback");
+            let backup = target.with_file_name(name);
+            // fs::copy carries the permissions over, so a restored binary stays executable.
+            fs::copy(target, &backup)
+                .map_err(|e| AppError::io(format!("Failed to back up {}", target.display()), e))?;
+            Some(backup)
+        } else {
+            None
+        };
+        self.0.push((target.to_path_buf(), backup));
+        Ok(())
+    }
+
+    // Puts every file back it can. A backup that couldn't be moved into place stays on disk,
+    // and the error lists what is left to restore by hand.
+    fn restore(self) -> Result<(), AppError> {
+        let mut failed = Vec::new();
+        for (target, backup) in self.0.into_iter().rev() {
+            let restored = match &backup {
+                Some(backup) => fs::rename(backup, &target),
+                None => match fs::remove_file(&target) {
+                    Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+                    other => other,
+                },
+            };
+            if let Err(err) = restored {
+                error!("Failed to restore {}: {}", target.display(), err);
+                failed.push(match backup {
+                    Some(backup) => format!("{} (from {})", target.display(), backup.display()),
+                    None => format!("{} (should be removed)", target.display()),
+                });
+            }
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::RollbackFailed(format!(
+                "The previous sidecar couldn't be fully restored: {}",
+                failed.join(", ")
+            )))
+        }
+    }
+
+    fn discard(self) {
+        for (_, backup) in self.0 {
+            if let Some(backup) = backup {
+                let _ = fs::remove_file(backup);
+            }
+        }
+    }
+}
+
+// Puts a verified download in place of `installed`: staged and fsynced next to it with the
+// installed binary's permissions, then renamed over it. Its checksum file is rewritten (and
+// checksum files in other algorithms removed so they can't win), and `.sig` / `.version`
+// follow the download. Anything that fails undoes the steps before it.
+fn install_sidecar_update(
+    download: &Path,
+    installed: &Path,
+    algo: HashAlgorithm,
+    digest: &str,
+) -> Result<UpdateRollback, AppError> {
+    let mut rollback = UpdateRollback(Vec::new());
+    match swap_in_sidecar_update(download, installed, algo, digest, &mut rollback) {
+        Ok(()) => Ok(rollback),
+        Err(err) => {
+            rollback.restore().map_err(|restore_err| {
+                AppError::RollbackFailed(format!(
+                    "{} after a failed update ({})",
+                    restore_err.message(),
+                    err
+                ))
+            })?;
+            Err(err)
+        }
+    }
+}
+
+fn swap_in_sidecar_update(
+    download: &Path,
+    installed: &Path,
+    algo: HashAlgorithm,
+    digest: &str,
+    rollback: &mut UpdateRollback,
+) -> Result<(), AppError> {
+    let file_name = installed
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let download_name = download
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let companion = |ext: &str| installed.with_file_name(format!("{}.{}", file_name, ext));
+
+    let staged = unique_tmp_path(installed);
+    let swapped = copy_file_verified(download, &staged, |_| {})
+        .and_then(|_| {
+            let handle = fs::OpenOptions::new()
+                .write(true)
+                .open(&staged)
+                .map_err(|e| AppError::io(format!("Failed to open {}", staged.display()), e))?;
+            match_target_permissions(&handle, installed)
+        })
+        .and_then(|()| rollback.save(installed))
+        .and_then(|()| {
+            fs::rename(&staged, installed)
+                .map_err(|e| AppError::io(format!("Failed to replace {}", installed.display()), e))
+        });
+    if let Err(err) = swapped {
+        let _ = fs::remove_file(&staged);
+        return Err(err);
+    }
+    sync_parent_dir(installed)?;
+
+    for other in HashAlgorithm::PREFERENCE {
+        let path = companion(other.extension());
+        if path.exists() {
+            rollback.save(&path)?;
+            fs::remove_file(&path)
+                .map_err(|e| AppError::io(format!("Failed to remove {}", path.display()), e))?;
+        }
+    }
+    let checksum_path = companion(algo.extension());
+    rollback.save(&checksum_path)?;
+    write_atomic(
+        &checksum_path,
+        format!("{}  {}\n", digest, file_name).as_bytes(),
+    )?;
+
+    for ext in ["sig", "version"] {
+        let source = download.with_file_name(format!("{}.{}", download_name, ext));
+        let target = companion(ext);
+        rollback.save(&target)?;
+        if source.exists() {
+            fs::copy(&source, &target)
+                .map_err(|e| AppError::io(format!("Failed to install {}", target.display()), e))?;
+        } else if target.exists() {
+            fs::remove_file(&target)
+                .map_err(|e| AppError::io(format!("Failed to remove {}", target.display()), e))?;
+        }
+    }
+    Ok(())
+}
+
+// Replaces the installed default sidecar with a downloaded build. The download needs a checksum
+// next to it (and a signature when this build embeds a signing key) and must match the app's
+// architecture. A running sidecar is stopped first and restarted on the new binary; if that
+// start fails, the previous binary is put back and started again. Resolves to whether the
+// sidecar was restarted.
+#[tauri::command]
+async fn apply_sidecar_update(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    app: tauri::AppHandle,
+    download_path: String,
+) -> Result<bool, AppError> {
+    ensure_writable(&app)?;
+    let download = PathBuf::from(&download_path);
+    if !download.is_absolute() {
+        return Err(AppError::InvalidInput(
+            "The downloaded sidecar path must be absolute".to_string(),
+        ));
+    }
+    if sidecar_path_override(DEFAULT_SIDECAR_NAME).is_some() {
+        return Err(AppError::InvalidInput(
+            "PROJECT_DAWN_SIDECAR_PATH is set; the installed sidecar isn't in use".to_string(),
+        ));
+    }
+    if state.lock().await.external {
+        return Err(AppError::SidecarUnavailable(
+            "An external sidecar can't be updated from the app".to_string(),
+        ));
+    }
+
+    let task_app = app.clone();
+    let task_download = download.clone();
+    let (installed, algo, digest) = tauri::async_runtime::spawn_blocking(move || {
+        let download = task_download;
+        let checksum_path = checksum_path_for(&download);
+        if !checksum_path.exists() {
+            return Err(AppError::IntegrityFailed(format!(
+                "{} has no checksum next to it",
+                download.display()
+            )));
+        }
+        let report =
+            inspect_sidecar_at(&task_app, DEFAULT_SIDECAR_NAME, &download, &checksum_path)?;
+        if !report.checksum_ok {
+            return Err(AppError::IntegrityFailed(format!(
+                "Downloaded sidecar checksum mismatch ({})",
+                report.algorithm.extension()
+            )));
+        }
+        if let Some(err) = report.signature_error {
+            return Err(err);
+        }
+        if SIDECAR_SIGNING_PUBKEY.is_some() && !report.signed {
+            return Err(AppError::IntegrityFailed(
+                "Downloaded sidecar is not signed".to_string(),
+            ));
+        }
+        check_sidecar_architecture(&download)?;
+        let installed = locate_sidecar_binary(&task_app, DEFAULT_SIDECAR_NAME)?;
+        Ok((installed, report.algorithm, report.actual))
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Update verification task failed: {e}")))??;
+
+    let state = state.inner();
+    let guard = state.lock().await;
+    // Held from the shutdown until the sidecar is started again, so start_sidecar, autostart
+    // and the supervisor can't launch a binary that is being replaced.
+    let Some(update_lock) = TaskFlagGuard::claim(&guard.updating) else {
+        return Err(AppError::SidecarUnavailable(
+            "A sidecar update is already being applied".to_string(),
+        ));
+    };
+    let updating = guard.updating.clone();
+    let was_running = guard.mode() != SidecarMode::Stopped;
+    let (port, metrics_port) = (guard.port, guard.metrics_port);
+    let extra_env = guard.extra_env.clone();
+    drop(guard);
+    let shutdown_timeout = Duration::from_millis(DEFAULT_SHUTDOWN_TIMEOUT_MS);
+    if shutdown_sidecar(state, shutdown_timeout).await != StopOutcome::NotRunning {
+        for port in [port, metrics_port] {
+            if !wait_for_port_free(port, shutdown_timeout).await {
+                warn!("Port {} still in use after stopping the sidecar", port);
+            }
+        }
+    }
+
+    let task_installed = installed.clone();
+    let rollback = tauri::async_runtime::spawn_blocking(move || {
+        install_sidecar_update(&download, &task_installed, algo, &digest)
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Update install task failed: {e}")))?;
+    let rollback = match rollback {
+        Ok(rollback) => rollback,
+        Err(err @ AppError::RollbackFailed(_)) => {
+            error!("Sidecar update failed and couldn't be undone: {}", err);
+            return Err(err);
+        }
+        Err(err) => {
+            error!("Sidecar update failed, previous binary kept: {}", err);
+            drop(update_lock);
+            if was_running {
+                if let Err(restart_err) =
+                    start_and_maybe_wait(state, &app, extra_env, None, None).await
+                {
+                    error!("Previous sidecar failed to start again: {}", restart_err);
+                }
+            }
+            return Err(err);
+        }
+    };
+    info!("Installed sidecar update at {}", installed.display());
+
+    // In maintenance mode the new binary waits for the mode to be turned off.
+    if !was_running || ensure_not_in_maintenance(&app).is_err() {
+        rollback.discard();
+        return Ok(false);
+    }
+    drop(update_lock);
+    let ready_timeout = Some(DEFAULT_RESTART_READY_TIMEOUT_MS);
+    match start_and_maybe_wait(state, &app, extra_env.clone(), ready_timeout, None).await {
+        Ok(_) => {
+            rollback.discard();
+            Ok(true)
+        }
+        Err(err) => {
+            error!("Updated sidecar failed to start ({}); rolling back", err);
+            let Some(update_lock) = TaskFlagGuard::claim(&updating) else {
+                // Another update took over; leave the .rollback files for it or for the user.
+                return Err(AppError::RollbackFailed(format!(
+                    "The new sidecar failed to start ({}) and another update is in progress; \
+                     the previous files were left as .rollback next to {}",
+                    err,
+                    installed.display()
+                )));
+            };
+            shutdown_sidecar(state, shutdown_timeout).await;
+            tauri::async_runtime::spawn_blocking(move || rollback.restore())
+                .await
+                .map_err(|e| AppError::Io(format!("Update rollback task failed: {e}")))?
+                .map_err(|restore_err| {
+                    error!("Sidecar rollback failed: {}", restore_err);
+                    AppError::RollbackFailed(format!(
+                        "The new sidecar failed to start ({}); {}",
+                        err,
+                        restore_err.message()
+                    ))
+                })?;
+            drop(update_lock);
+            if let Err(restart_err) =
+                start_and_maybe_wait(state, &app, extra_env, ready_timeout, None).await
+            {
+                error!(
+                    "Previous sidecar failed to start after the rollback: {}",
+                    restart_err
+                );
+            }
+            Err(AppError::SidecarUnavailable(format!(
+                "Update rolled back; the new sidecar failed to start: {}",
+                err
+            )))
+        }
+    }
+}
+
+// The one WebSocket connection to the default sidecar. The UI talks through it with
+// `send_to_sidecar` and the `sidecar_message` event instead of opening its own socket.
+struct SidecarSocket {
+    outbound: tokio::sync::mpsc::UnboundedSender<String>,
+    connected: Arc<AtomicBool>,
+}
+
+fn start_sidecar_socket(app: tauri::AppHandle) -> SidecarSocket {
+    let (outbound, rx) = tokio::sync::mpsc::unbounded_channel();
+    let connected = Arc::new(AtomicBool::new(false));
+    let task = run_sidecar_socket(app, rx, connected.clone());
+    tauri::async_runtime::spawn(task.instrument(tracing::info_span!("sidecar_socket")));
+    SidecarSocket {
+        outbound,
+        connected,
+    }
+}
+
+async fn set_socket_connected(
+    app: &tauri::AppHandle,
+    state: &Arc<Mutex<SidecarState>>,
+    connected: &AtomicBool,
+    value: bool,
+) {
+    connected.store(value, Ordering::SeqCst);
+    state.lock().await.health.socket_connected = value;
+    let _ = app.emit_all(
+        "sidecar_connection",
+        serde_json::json!({ "connected": value }),
+    );
+}
+
+// Connects whenever the default sidecar is up and reconnects with backoff after a drop, reading
+// the port afresh each time so restarts and attaches are followed.
+async fn run_sidecar_socket(
+    app: tauri::AppHandle,
+    mut outbound: tokio::sync::mpsc::UnboundedReceiver<String>,
+    connected: Arc<AtomicBool>,
+) {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let state = app.state::<Arc<Mutex<SidecarState>>>().inner().clone();
+    let mut attempt = 0u32;
+    loop {
+        let port = {
+            let guard = state.lock().await;
+            matches!(guard.mode(), SidecarMode::Running | SidecarMode::External)
+                .then_some(guard.port)
+        };
+        let Some(port) = port else {
+            attempt = 0;
+            tokio::time::sleep(READY_POLL_INTERVAL).await;
+            continue;
+        };
+        let url = format!("ws://127.0.0.1:{}/", port);
+        let socket = match tokio_tungstenite::connect_async(url.as_str()).await {
+            Ok((socket, _)) => socket,
+            Err(err) => {
+                warn!("Sidecar connection to {} failed: {}", url, err);
+                tokio::time::sleep(restart_backoff(attempt)).await;
+                attempt = attempt.saturating_add(1);
+                continue;
+            }
+        };
+        attempt = 0;
+        // Anything queued while disconnected was addressed to the previous session.
+        while outbound.try_recv().is_ok() {}
+        info!("Sidecar connection open on port {}", port);
+        set_socket_connected(&app, &state, &connected, true).await;
+
+        let (mut sink, mut stream) = socket.split();
+        let reason = loop {
+            tokio::select! {
+                incoming = stream.next() => match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        let _ = app.emit_all(
+                            "sidecar_message",
+                            serde_json::json!({ "message": text }),
+                        );
+                    }
+                    Some(Ok(Message::Close(frame))) => {
+                        break format!("closed by the sidecar ({:?})", frame);
+                    }
+                    // Pings are answered by tungstenite; the protocol has no binary messages.
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => break err.to_string(),
+                    None => break "connection closed".to_string(),
+                },
+                message = outbound.recv() => match message {
+                    Some(message) => {
+                        if let Err(err) = sink.send(Message::Text(message)).await {
+                            break err.to_string();
+                        }
+                    }
+                    // The app is shutting down.
+                    None => return,
+                },
+            }
+        };
+        warn!("Sidecar connection lost: {}", reason);
+        set_socket_connected(&app, &state, &connected, false).await;
+        tokio::time::sleep(restart_backoff(0)).await;
+    }
+}
+
+// Entry guard for commands that only make sense against a live sidecar, so they fail up front
+// instead of after touching anything. An attached external sidecar counts.
+async fn require_sidecar(state: &Arc<Mutex<SidecarState>>) -> Result<(), AppError> {
+    let guard = state.lock().await;
+    if guard.mode() == SidecarMode::Stopped {
+        return Err(AppError::SidecarUnavailable(format!(
+            "Sidecar {} is not running",
+            guard.name
+        )));
+    }
+    Ok(())
+}
+
+// Strings go out as-is; anything else is sent as its JSON text.
+#[tauri::command]
+async fn send_to_sidecar(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    socket: State<'_, SidecarSocket>,
+    app: tauri::AppHandle,
+    msg: serde_json::Value,
+) -> Result<(), AppError> {
+    ensure_writable(&app)?;
+    require_sidecar(state.inner()).await?;
+    if !socket.connected.load(Ordering::SeqCst) {
+        return Err(AppError::SidecarUnavailable(
+            "Not connected to the sidecar".to_string(),
+        ));
+    }
+    let text = match msg {
+        serde_json::Value::String(text) => text,
+        other => other.to_string(),
+    };
+    socket.outbound.send(text).map_err(|_| {
+        AppError::SidecarUnavailable("The sidecar connection has shut down".to_string())
+    })
+}
+
+fn data_root<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> PathBuf {
+    if let Ok(override_path) = std::env::var("PROJECT_DAWN_DATA_ROOT") {
+        return PathBuf::from(override_path);
+    }
+    if let Some(migrated) = read_data_root_override(app) {
+        return migrated;
+    }
+    tauri::api::path::app_data_dir(&app.config())
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+}
+
+// Written by migrate_data_root. Lives in the config dir, not the data root it points away from.
+fn data_root_override_file<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Option<PathBuf> {
+    tauri::api::path::app_config_dir(&app.config()).map(|dir| dir.join("data_root.json"))
+}
+
+fn read_data_root_override<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Option<PathBuf> {
+    let contents = fs::read_to_string(data_root_override_file(app)?).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    value.get("path")?.as_str().map(PathBuf::from)
+}
+
+// Per-user preferences; like data_root.json they live in the config dir, not the data root.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct AppSettings {
+    // Start the default sidecar on launch instead of waiting for the UI to.
+    autostart: bool,
+    // Where check_sidecar_update looks for new sidecar builds; `PROJECT_DAWN_UPDATE_URL` wins.
+    update_manifest_url: Option<String>,
+}
+
+fn app_settings_path(app: &tauri::AppHandle) -> Option<PathBuf> {
+    tauri::api::path::app_config_dir(&app.config()).map(|dir| dir.join("app_settings.json"))
+}
+
+fn load_app_settings(app: &tauri::AppHandle) -> AppSettings {
+    let Some(path) = app_settings_path(app) else {
+        return AppSettings::default();
+    };
+    match read_optional_file(path.clone()) {
+        Ok(Some(contents)) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            warn!("Invalid {}: {e}; using defaults", path.display());
+            AppSettings::default()
+        }),
+        Ok(None) => AppSettings::default(),
+        Err(err) => {
+            warn!("{}; using default app settings", err);
+            AppSettings::default()
+        }
+    }
+}
+
+fn update_manifest_url(app: &tauri::AppHandle) -> Option<String> {
+    std::env::var("PROJECT_DAWN_UPDATE_URL")
+        .ok()
+        .or_else(|| load_app_settings(app).update_manifest_url)
+        .filter(|url| !url.trim().is_empty())
+}
+
+#[tauri::command]
+fn get_autostart(app: tauri::AppHandle) -> Result<bool, AppError> {
+    Ok(load_app_settings(&app).autostart)
+}
+
+// Takes effect on the next launch; it doesn't start or stop anything now.
+#[tauri::command]
+fn set_autostart(app: tauri::AppHandle, enabled: bool) -> Result<(), AppError> {
+    ensure_writable(&app)?;
+    let path = app_settings_path(&app)
+        .ok_or_else(|| AppError::Io("No config directory to store app settings".to_string()))?;
+    let mut settings = load_app_settings(&app);
+    settings.autostart = enabled;
+    let payload = serde_json::to_string_pretty(&settings)
+        .map_err(|e| AppError::InvalidInput(format!("Failed to serialize app settings: {e}")))?;
+    write_json_atomic(&path, &payload)
+}
+
+// Runs once from setup. A failure can't be returned to anyone, so it's logged and announced as
+// `autostart_failed` for the UI to show.
+async fn autostart_sidecar(app: tauri::AppHandle, state: Arc<Mutex<SidecarState>>) {
+    info!("Autostart enabled, starting the sidecar");
+    if let Err(err) = start_and_maybe_wait(&state, &app, HashMap::new(), None, None).await {
+        error!("Autostart failed: {}", err);
+        let _ = app.emit_all("autostart_failed", serde_json::json!({ "error": err }));
+    }
+}
+
+#[derive(Serialize)]
+struct DataRootInfo {
+    path: String,
+    exists: bool,
+    error: Option<AppError>,
+}
+
+// Result of ensure_data_root at startup, kept for get_data_root since the `data_root_error`
+// event may fire before the UI is listening.
+struct DataRootCheck(Option<AppError>);
+
+// Creates `vault/` and `mesh/` and proves the root is writable, so a bad location fails here
+// with a clear message rather than on the first write deep inside some command.
+fn ensure_data_root(root: &Path) -> Result<(), AppError> {
+    if root.exists() && !root.is_dir() {
+        return Err(AppError::InvalidInput(format!(
+            "Data root {} is not a directory",
+            root.display()
+        )));
+    }
+    for dir in ["vault", "mesh"] {
+        let dir = root.join(dir);
+        fs::create_dir_all(&dir)
+            .map_err(|e| AppError::io(format!("Failed to create {}", dir.display()), e))?;
+    }
+    let probe = root.join(format!(".write_probe.{}", std::process::id()));
+    let written = fs::write(&probe, b"ok");
+    let _ = fs::remove_file(&probe);
+    written.map_err(|e| AppError::io(format!("Data root {} is not writable", root.display()), e))
+}
+
+#[tauri::command]
+fn get_data_root(
+    app: tauri::AppHandle,
+    check: State<'_, DataRootCheck>,
+) -> Result<DataRootInfo, AppError> {
+    let root = data_root(&app);
+    Ok(DataRootInfo {
+        path: root.to_string_lossy().to_string(),
+        exists: root.is_dir(),
+        error: check.0.clone(),
+    })
+}
+
+const MIGRATED_DIRS: [&str; 2] = ["vault", "mesh"];
+
+#[derive(Serialize)]
+struct DataRootMigration {
+    old_path: String,
+    new_path: String,
+    files_copied: usize,
+    bytes_copied: u64,
+    // Monitors and watchers keep the old paths until the app is restarted.
+    restart_required: bool,
+}
+
+fn dir_has_entries(path: &Path) -> bool {
+    fs::read_dir(path)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false)
+}
+
+// Lists every file under `src` (relative to `base`) with its size, skipping leftover temp files.
+fn collect_tree(src: &Path, base: &Path, files: &mut Vec<(PathBuf, u64)>) -> Result<(), AppError> {
+    let entries = fs::read_dir(src)
+        .map_err(|e| AppError::io(format!("Failed to list {}", src.display()), e))?;
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| AppError::io(format!("Failed to list {}", src.display()), e))?;
+        let path = entry.path();
+        if path.is_dir() {
+            collect_tree(&path, base, files)?;
+        } else if path.extension().map_or(false, |ext| ext == "tmp") {
+            continue;
+        } else {
+            let size = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+            files.push((path.strip_prefix(base).unwrap_or(&path).to_path_buf(), size));
+        }
+    }
+    Ok(())
+}
+
+// Streams `from` into `to`, hashing on the way, then re-reads the copy to prove it landed intact.
+fn copy_file_verified(
+    from: &Path,
+    to: &Path,
+    mut progress: impl FnMut(u64),
+) -> Result<u64, AppError> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| AppError::io(format!("Failed to create {}", parent.display()), e))?;
+    }
+    let mut output = std::io::BufWriter::new(
+        File::create(to)
+            .map_err(|e| AppError::io(format!("Failed to create {}", to.display()), e))?,
+    );
+    let mut hasher = Sha256::new();
+    let mut copied = 0u64;
+    let mut write_error = None;
+    stream_file(from, |chunk| {
+        if write_error.is_some() {
+            return;
+        }
+        match output.write_all(chunk) {
+            Ok(()) => {
+                hasher.update(chunk);
+                copied += chunk.len() as u64;
+                progress(chunk.len() as u64);
+            }
+            Err(err) => write_error = Some(err),
+        }
+    })?;
+    if let Some(err) = write_error {
+        return Err(AppError::io(
+            format!("Failed to write {}", to.display()),
+            err,
+        ));
+    }
+    let file = output
+        .into_inner()
+        .map_err(|e| AppError::io(format!("Failed to write {}", to.display()), e.into_error()))?;
+    file.sync_all()
+        .map_err(|e| AppError::io(format!("Failed to sync {}", to.display()), e))?;
+
+    if hash_file(to, HashAlgorithm::Sha256)? != hasher.finalize().to_vec() {
+        return Err(AppError::IntegrityFailed(format!(
+            "Copy of {} does not match the original",
+            from.display()
+        )));
+    }
+    Ok(copied)
+}
+
+// Copies `vault/` and `mesh/` file by file, emitting `migration_progress` along the way.
+fn copy_data_root(
+    app: &tauri::AppHandle,
+    old_root: &Path,
+    new_root: &Path,
+) -> Result<(usize, u64), AppError> {
+    let mut files = Vec::new();
+    for dir in MIGRATED_DIRS {
+        let src = old_root.join(dir);
+        if src.is_dir() {
+            collect_tree(&src, old_root, &mut files)?;
+        }
+    }
+    let total_files = files.len();
+    let total_bytes: u64 = files.iter().map(|(_, size)| size).sum();
+    let mut bytes_copied = 0u64;
+    let mut last_emit: Option<Instant> = None;
+    let mut emit = |files_copied: usize, bytes_copied: u64, force: bool| {
+        // Large files report as they stream, but no more often than every 100ms.
+        if !force && last_emit.map_or(false, |at| at.elapsed() < MIGRATION_PROGRESS_INTERVAL) {
+            return;
+        }
+        last_emit = Some(Instant::now());
+        let _ = app.emit_all(
+            "migration_progress",
+            serde_json::json!({
+                "files_copied": files_copied,
+                "total_files": total_files,
+                "bytes_copied": bytes_copied,
+                "total_bytes": total_bytes,
+            }),
+        );
+    };
+    emit(0, 0, true);
+    for (index, (relative, _)) in files.iter().enumerate() {
+        let file_start = bytes_copied;
+        let copied = copy_file_verified(
+            &old_root.join(relative),
+            &new_root.join(relative),
+            |chunk_len| {
+                bytes_copied += chunk_len;
+                emit(index, bytes_copied, false);
+            },
+        )?;
+        bytes_copied = file_start + copied;
+        emit(index + 1, bytes_copied, true);
+    }
+    Ok((total_files, bytes_copied))
+}
+
+fn migrate_data_root_blocking(
+    app: &tauri::AppHandle,
+    new_root: PathBuf,
+) -> Result<DataRootMigration, AppError> {
+    let old_root = data_root(app);
+    if !new_root.is_absolute() {
+        return Err(AppError::InvalidInput(format!(
+            "{} is not an absolute path",
+            new_root.display()
+        )));
+    }
+    if new_root.starts_with(&old_root) || old_root.starts_with(&new_root) {
+        return Err(AppError::InvalidInput(format!(
+            "{} overlaps the current data root {}",
+            new_root.display(),
+            old_root.display()
+        )));
+    }
+    if let Some(dir) = MIGRATED_DIRS
+        .iter()
+        .map(|dir| new_root.join(dir))
+        .find(|dir| dir_has_entries(dir))
+    {
+        return Err(AppError::InvalidInput(format!(
+            "{} already contains data; move it aside first",
+            dir.display()
+        )));
+    }
+    ensure_data_root(&new_root)?;
+
+    let (files_copied, bytes_copied) = match copy_data_root(app, &old_root, &new_root) {
+        Ok(result) => result,
+        Err(err) => {
+            // The destination held no data before, so it's safe to clear out the partial copy.
+            for dir in MIGRATED_DIRS {
+                let _ = fs::remove_dir_all(new_root.join(dir));
+            }
+            return Err(err);
+        }
+    };
+
+    let override_file = data_root_override_file(app).ok_or_else(|| {
+        AppError::Io("No config directory to record the new data root".to_string())
+    })?;
+    let payload = serde_json::json!({ "path": new_root.to_string_lossy() });
+    write_json_atomic(&override_file, &payload.to_string())?;
+
+    // Only now that the new location is recorded is the old copy expendable.
+    for dir in MIGRATED_DIRS {
+        let old_dir = old_root.join(dir);
+        if old_dir.exists() {
+            if let Err(err) = fs::remove_dir_all(&old_dir) {
+                warn!("Failed to remove {}: {}", old_dir.display(), err);
+            }
+        }
+    }
+    info!(
+        "Migrated data root from {} to {} ({} files)",
+        old_root.display(),
+        new_root.display(),
+        files_copied
+    );
+    Ok(DataRootMigration {
+        old_path: old_root.to_string_lossy().to_string(),
+        new_path: new_root.to_string_lossy().to_string(),
+        files_copied,
+        bytes_copied,
+        restart_required: true,
+    })
+}
+
+#[tauri::command]
+async fn migrate_data_root(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    app: tauri::AppHandle,
+    new_path: String,
+) -> Result<DataRootMigration, AppError> {
+    ensure_writable(&app)?;
+    if std::env::var_os("PROJECT_DAWN_DATA_ROOT").is_some() {
+        return Err(AppError::InvalidInput(
+            "The data root is set by PROJECT_DAWN_DATA_ROOT; change it there instead".to_string(),
+        ));
+    }
+    {
+        let guard = state.lock().await;
+        if guard.process.is_some() || guard.external {
+            return Err(AppError::SidecarUnavailable(
+                "Stop the sidecar before migrating the data root".to_string(),
+            ));
+        }
+    }
+    let new_root = PathBuf::from(new_path);
+    let task_app = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        migrate_data_root_blocking(&task_app, new_root)
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Data root migration task failed: {}", e)))
+    .and_then(|result| result);
+    match &result {
+        Ok(migration) => {
+            let _ = app.emit_all("migration_complete", migration);
+        }
+        Err(err) => {
+            let _ = app.emit_all("migration_failed", serde_json::json!({ "error": err }));
+        }
+    }
+    result
+}
+
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn unique_tmp_path(path: &Path) -> PathBuf {
+    let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}.tmp", std::process::id(), n));
+    path.with_file_name(name)
+}
+
+// Gives the temp file the mode (and on Unix, where we're allowed, the owner) of the file it is
+// about to replace, before anything is written to it, so a 0600 vault file never passes through
+// a world-readable copy.
+fn match_target_permissions(handle: &File, target: &Path) -> Result<(), AppError> {
+    let meta = match fs::metadata(target) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(AppError::io(
+                format!("Failed to stat {}", target.display()),
+                e,
+            ))
+        }
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        use std::os::unix::io::AsRawFd;
+        // Only root may give a file away; for everyone else this is a no-op or EPERM.
+        // SAFETY: fchown(2) only reads the descriptor, which `handle` keeps open.
+        let _ = unsafe { libc::fchown(handle.as_raw_fd(), meta.uid(), meta.gid()) };
+    }
+    handle.set_permissions(meta.permissions()).map_err(|e| {
+        AppError::io(
+            format!("Failed to set permissions on {}", target.display()),
+            e,
+        )
+    })
+}
+
+fn write_tmp_file(tmp_path: &Path, contents: &[u8], target: &Path) -> Result<(), AppError> {
+    let mut handle = File::create(tmp_path)
+        .map_err(|e| AppError::io(format!("Failed to create {}", tmp_path.display()), e))?;
+    match_target_permissions(&handle, target)?;
+    handle
+        .write_all(contents)
+        .map_err(|e| AppError::io(format!("Failed to write {}", tmp_path.display()), e))?;
+    handle
+        .flush()
+        .map_err(|e| AppError::io("Failed to flush", e))?;
+    handle
+        .sync_all()
+        .map_err(|e| AppError::io("Failed to sync", e))
+}
+
+/// Atomically replaces `path` with `payload` followed by a newline.
+fn write_json_atomic(path: &PathBuf, payload: &str) -> Result<(), AppError> {
+    write_atomic(path, format!("{payload}\n").as_bytes())
+}
+
+/// Atomically replaces `path` with exactly `contents`.
+///
+/// Each call writes to its own temp file (pid + counter) beside the target, fsyncs it and
+/// renames it over `path`. Readers see either the previous contents or one complete new
+/// payload, never a mix; when several writers race on the same path the last rename wins.
+/// On Unix the parent directory is fsynced afterwards so the rename itself survives a crash.
+/// An existing target's permissions (and owner, where allowed) carry over to the new file.
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), AppError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| AppError::io(format!("Failed to create {}", parent.display()), e))?;
+    }
+    let tmp_path = unique_tmp_path(path);
+    let result = write_tmp_file(&tmp_path, contents, path).and_then(|()| {
+        fs::rename(&tmp_path, path)
+            .map_err(|e| AppError::io(format!("Failed to move {}", path.display()), e))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result?;
+    sync_parent_dir(path)
+}
+
+/// Writes several files so that a failure or crash before the renames leaves every target
+/// untouched.
+///
+/// All payloads are written to fsynced temp files first, then renamed over their targets in
+/// order. Rename is only atomic per file, so a crash part-way through the renames can still
+/// leave some targets new and others old. With `journal`, the pending renames are recorded
+/// (and fsynced) there before the first one and the journal is removed after the last;
+/// [`recover_write_journal`] finishes an interrupted batch on the next launch.
+fn write_json_atomic_batch(
+    writes: Vec<(PathBuf, String)>,
+    journal: Option<&Path>,
+) -> Result<(), AppError> {
+    let mut staged: Vec<JournalRename> = Vec::with_capacity(writes.len());
+    for (path, payload) in writes {
+        let tmp_path = unique_tmp_path(&path);
+        let written = match path.parent() {
+            Some(parent) => fs::create_dir_all(parent)
+                .map_err(|e| AppError::io(format!("Failed to create {}", parent.display()), e)),
+            None => Ok(()),
+        }
+        .and_then(|()| write_tmp_file(&tmp_path, format!("{payload}\n").as_bytes(), &path));
+        staged.push(JournalRename {
+            from: tmp_path,
+            to: path,
+        });
+        if let Err(err) = written {
+            discard_staged(&staged);
+            return Err(err);
+        }
+    }
+    if let Some(journal) = journal {
+        let record = WriteJournal { renames: staged };
+        let payload = serde_json::to_string(&record)
+            .map_err(|e| AppError::Io(format!("Failed to encode write journal: {e}")))?;
+        if let Err(err) = write_json_atomic(&journal.to_path_buf(), &payload) {
+            discard_staged(&record.renames);
+            return Err(err);
+        }
+        staged = record.renames;
+    }
+
+    for (index, rename) in staged.iter().enumerate() {
+        let renamed = fs::rename(&rename.from, &rename.to)
+            .map_err(|e| AppError::io(format!("Failed to move {}", rename.to.display()), e))
+            .and_then(|()| sync_parent_dir(&rename.to));
+        if let Err(err) = renamed {
+            // With a journal the remaining temp files are left for recovery to finish.
+            if journal.is_none() {
+                discard_staged(&staged[index..]);
+            }
+            return Err(err);
+        }
+    }
+    if let Some(journal) = journal {
+        fs::remove_file(journal)
+            .map_err(|e| AppError::io(format!("Failed to remove {}", journal.display()), e))?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct WriteJournal {
+    renames: Vec<JournalRename>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalRename {
+    from: PathBuf,
+    to: PathBuf,
+}
+
+fn discard_staged(staged: &[JournalRename]) {
+    for rename in staged {
+        let _ = fs::remove_file(&rename.from);
+    }
+}
+
+fn write_journal_path(data_root: &Path) -> PathBuf {
+    data_root.join(".write_journal.json")
+}
+
+// Every temp file listed in a journal was complete and fsynced before the journal was
+// written, so renaming the ones still present finishes the batch. Returns how many were
+// replayed.
+fn recover_write_journal(journal: &Path) -> Result<usize, AppError> {
+    let Some(contents) = read_optional_file(journal.to_path_buf())? else {
+        return Ok(0);
+    };
+    let record: WriteJournal = serde_json::from_str(&contents).map_err(|e| {
+        AppError::IntegrityFailed(format!("{} is malformed: {}", journal.display(), e))
+    })?;
+    let mut replayed = 0;
+    for rename in &record.renames {
+        if rename.from.exists() {
+            fs::rename(&rename.from, &rename.to)
+                .map_err(|e| AppError::io(format!("Failed to move {}", rename.to.display()), e))?;
+            sync_parent_dir(&rename.to)?;
+            replayed += 1;
+        }
+    }
+    fs::remove_file(journal)
+        .map_err(|e| AppError::io(format!("Failed to remove {}", journal.display()), e))?;
+    Ok(replayed)
+}
+
+fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    if index == 0 {
+        name.push(".bak");
+    } else {
+        name.push(format!(".bak.{index}"));
+    }
+    PathBuf::from(name)
+}
+
+// Moves `<name>.bak` … `<name>.bak.<keep - 2>` one slot down, dropping the oldest.
+fn shift_backups(path: &Path, keep: usize) -> Result<(), AppError> {
+    for index in (0..keep.saturating_sub(1)).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, index + 1))
+                .map_err(|e| AppError::io(format!("Failed to rotate {}", from.display()), e))?;
+        }
+    }
+    Ok(())
+}
+
+// Copies rather than moves the current file so `path` never disappears between the backup
+// and the rename of the new contents.
+fn rotate_backups(path: &Path, keep: usize) -> Result<(), AppError> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+    shift_backups(path, keep)?;
+    let backup = backup_path(path, 0);
+    fs::copy(path, &backup)
+        .map(|_| ())
+        .map_err(|e| AppError::io(format!("Failed to back up {}", path.display()), e))
+}
+
+// Saves `contents` as the newest backup of `path`, for when the file itself has already been
+// replaced and only an earlier read of it is left.
+fn push_backup(path: &Path, contents: &str, keep: usize) -> Result<(), AppError> {
+    if keep == 0 {
+        return Ok(());
+    }
+    shift_backups(path, keep)?;
+    write_json_atomic(&backup_path(path, 0), contents.trim_end())
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> Result<(), AppError> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|e| AppError::io(format!("Failed to sync {}", parent.display()), e))
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> Result<(), AppError> {
+    // Directories can't be opened for fsync on Windows; NTFS journals the rename itself.
+    Ok(())
+}
+
+// Errors seen while another process swaps the file in: the old file vanishing under a rename,
+// EBUSY, or a Windows sharing violation. They clear up on their own within milliseconds.
+fn is_transient_read_error(err: &std::io::Error) -> bool {
+    match err.kind() {
+        ErrorKind::NotFound | ErrorKind::Interrupted | ErrorKind::WouldBlock => true,
+        _ => {
+            #[cfg(unix)]
+            let busy = err.raw_os_error() == Some(libc::EBUSY);
+            // ERROR_ACCESS_DENIED (pending delete), ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+            #[cfg(windows)]
+            let busy = matches!(err.raw_os_error(), Some(5 | 32 | 33));
+            #[cfg(not(any(unix, windows)))]
+            let busy = false;
+            busy
+        }
+    }
+}
+
+// Retries `read` a few times while it fails with a transient error. Sleeps the thread between
+// attempts, so async code calls the readers built on it through `off_runtime`.
+fn retry_transient<T>(mut read: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+    let mut attempt = 1;
+    loop {
+        match read() {
+            Err(e) if attempt < READ_RETRY_ATTEMPTS && is_transient_read_error(&e) => {
+                attempt += 1;
+                std::thread::sleep(READ_RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+// A missing file is `None`. A file that exists but can't be read because it's mid-swap is
+// retried a few times before the error is surfaced; if it's gone for good by then, that's `None`.
+fn read_optional_file(path: PathBuf) -> Result<Option<String>, AppError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    match retry_transient(|| fs::read_to_string(&path)) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(AppError::io(
+            format!("Failed to read {}", path.display()),
+            e,
+        )),
+    }
+}
+
+// Runs blocking file work (anything that goes through read_optional_file) on the blocking pool
+// instead of stalling an async task.
+async fn off_runtime<T: Send + 'static>(
+    task: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, AppError> {
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| AppError::Io(format!("Blocking task failed: {}", e)))
+}
+
+// Falls back to `<path>.gz` when the plain file is absent; the plain file wins if both exist.
+// A truncated or corrupt stream is an error, never partial contents.
+fn read_optional_file_or_gz(path: PathBuf) -> Result<Option<String>, AppError> {
+    use flate2::read::GzDecoder;
+
+    if path.exists() {
+        return read_optional_file(path);
+    }
+    let mut gz_path = path.into_os_string();
+    gz_path.push(".gz");
+    let gz_path = PathBuf::from(gz_path);
+    if !gz_path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(&gz_path)
+        .map_err(|e| AppError::io(format!("Failed to open {}", gz_path.display()), e))?;
+    let mut contents = String::new();
+    GzDecoder::new(file)
+        .read_to_string(&mut contents)
+        .map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof | ErrorKind::InvalidInput | ErrorKind::InvalidData => {
+                AppError::IntegrityFailed(format!(
+                    "{} is truncated or corrupt: {}",
+                    gz_path.display(),
+                    e
+                ))
+            }
+            _ => AppError::io(format!("Failed to read {}", gz_path.display()), e),
+        })?;
+    Ok(Some(contents))
+}
+
+struct RotatingLog {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingLog {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: None,
+            size: 0,
+        }
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_os_string();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    fn open(&mut self) -> Result<&mut File, AppError> {
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent).map_err(|e| {
+                    AppError::io(format!("Failed to create {}", parent.display()), e)
+                })?;
+            }
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(|e| AppError::io(format!("Failed to open {}", self.path.display()), e))?;
+            self.size = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().expect("log file opened above"))
+    }
+
+    fn rotate(&mut self) -> Result<(), AppError> {
+        self.file = None;
+        for index in (1..SIDECAR_LOG_KEEP).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))
+                    .map_err(|e| AppError::io(format!("Failed to rotate {}", from.display()), e))?;
+            }
+        }
+        if self.path.exists() {
+            fs::rename(&self.path, self.rotated_path(1)).map_err(|e| {
+                AppError::io(format!("Failed to rotate {}", self.path.display()), e)
+            })?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    fn append(&mut self, line: &str) -> Result<(), AppError> {
+        self.open()?;
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > SIDECAR_LOG_MAX_BYTES {
+            self.rotate()?;
+        }
+        let file = self.open()?;
+        writeln!(file, "{}", line).map_err(|e| AppError::io("Failed to write log", e))?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+// Shared by every supervisor task so rotation never races a second writer.
+struct SidecarLog(std::sync::Mutex<RotatingLog>);
+
+// Off by default; a dev panel turns it on with set_sidecar_log_streaming.
+struct SidecarLogStreaming(AtomicBool);
+
+fn log_sidecar_line(app: &tauri::AppHandle, stream: &str, line: &str) {
+    let now = chrono::Utc::now();
+    {
+        let log = app.state::<SidecarLog>();
+        let mut log = match log.0.lock() {
+            Ok(log) => log,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let entry = format!("{} [{}] {}", now.to_rfc3339(), stream, line);
+        if let Err(err) = log.append(&entry) {
+            warn!("{}", err);
+        }
+    }
+    if app.state::<SidecarLogStreaming>().0.load(Ordering::Relaxed) {
+        let _ = app.emit_all(
+            "sidecar_log",
+            serde_json::json!({ "stream": stream, "line": line, "ts": now.timestamp_millis() }),
+        );
+    }
+}
+
+#[tauri::command]
+fn set_sidecar_log_streaming(
+    streaming: State<'_, SidecarLogStreaming>,
+    enabled: bool,
+) -> Result<bool, AppError> {
+    streaming.0.store(enabled, Ordering::Relaxed);
+    Ok(enabled)
+}
+
+const TAIL_CHUNK_BYTES: u64 = 64 * 1024;
+
+// Reads backwards from the end in fixed-size chunks, handing `visit` each cleaned line newest
+// first until it returns false, so the cost scales with how far back the caller looks rather
+// than the file size. Lines are split on raw bytes before decoding, which keeps multi-byte
+// UTF-8 sequences straddling a chunk boundary intact.
+fn for_each_line_rev(path: &Path, mut visit: impl FnMut(&str) -> bool) -> Result<(), AppError> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(AppError::io(
+                format!("Failed to open {}", path.display()),
+                e,
+            ))
+        }
+    };
+    let len = file
+        .metadata()
+        .map_err(|e| AppError::io(format!("Failed to stat {}", path.display()), e))?
+        .len();
+
+    // Chunks read since the last newline, newest first: a line that may continue further back.
+    // Kept apart and joined once the line is complete, so a very long line costs one copy.
+    let mut partial: Vec<Vec<u8>> = Vec::new();
+    let joined = |head: &[u8], partial: &mut Vec<Vec<u8>>| -> Vec<u8> {
+        let mut line = head.to_vec();
+        line.extend(partial.drain(..).rev().flatten());
+        line
+    };
+    let mut pos = len;
+    while pos > 0 {
+        let size = TAIL_CHUNK_BYTES.min(pos);
+        pos -= size;
+        file.seek(SeekFrom::Start(pos))
+            .map_err(|e| AppError::io(format!("Failed to seek {}", path.display()), e))?;
+        let mut chunk = vec![0u8; size as usize];
+        file.read_exact(&mut chunk)
+            .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+        let Some(first_newline) = chunk.iter().position(|byte| *byte == b'\n') else {
+            partial.push(chunk);
+            continue;
+        };
+        let complete = joined(&chunk[first_newline + 1..], &mut partial);
+        chunk.truncate(first_newline);
+        partial.push(chunk);
+        for line in String::from_utf8_lossy(&complete).split('\n').rev() {
+            if clean_feed_line(line).is_some_and(|line| !visit(line)) {
+                return Ok(());
+            }
+        }
+    }
+    let first = joined(&[], &mut partial);
+    if let Some(line) = clean_feed_line(&String::from_utf8_lossy(&first)) {
+        visit(line);
+    }
+    Ok(())
+}
+
+// The last `limit` lines, oldest first.
+fn tail_lines(path: &PathBuf, limit: usize) -> Result<Vec<String>, AppError> {
+    let mut lines = Vec::new();
+    if limit == 0 {
+        return Ok(lines);
+    }
+    for_each_line_rev(path, |line| {
+        lines.push(line.to_string());
+        lines.len() < limit
+    })?;
+    lines.reverse();
+    Ok(lines)
+}
+
+// Strips what some writers leave around a JSON line: a UTF-8 BOM and a trailing `\r`. None for
+// a blank line, which readers skip.
+fn clean_feed_line(line: &str) -> Option<&str> {
+    let line = line.strip_prefix('\u{feff}').unwrap_or(line);
+    let line = line.trim_end_matches('\r');
+    (!line.trim().is_empty()).then_some(line)
+}
+
+#[tauri::command]
+fn get_sidecar_log(app: tauri::AppHandle, lines: usize) -> Result<Vec<String>, AppError> {
+    let path = data_root(&app).join("logs").join("sidecar.log");
+    tail_lines(&path, lines)
+}
+
+const FOLLOWED_LOGS: [&str; 2] = ["sidecar", "app"];
+
+// Active follow_sidecar_log watchers, keyed by log name; dropping one stops it.
+struct LogFollowers(std::sync::Mutex<HashMap<String, notify::RecommendedWatcher>>);
+
+#[cfg(unix)]
+fn file_identity(meta: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.ino())
+}
+
+// No stable file id on stable Rust here; a rotated log shows up as the file shrinking instead.
+#[cfg(not(unix))]
+fn file_identity(_meta: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+// `tail -f` over a log that RotatingLog may rename to `<log>.1` at any point.
+struct LogFollower {
+    path: PathBuf,
+    offset: u64,
+    identity: Option<u64>,
+}
+
+impl LogFollower {
+    // Starts at the current end; get_sidecar_log covers what's already there.
+    fn new(path: PathBuf) -> Self {
+        let meta = fs::metadata(&path).ok();
+        Self {
+            offset: meta.as_ref().map(|meta| meta.len()).unwrap_or(0),
+            identity: meta.as_ref().and_then(file_identity),
+            path,
+        }
+    }
+
+    fn read_new_lines(&mut self) -> Result<Vec<String>, AppError> {
+        let identity = match fs::metadata(&self.path) {
+            Ok(meta) => file_identity(&meta),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(AppError::io(
+                    format!("Failed to stat {}", self.path.display()),
+                    e,
+                ))
+            }
+        };
+        let mut lines = Vec::new();
+        if identity != self.identity {
+            // Rotated: finish what was appended to the old file before it was renamed away.
+            let mut rotated = self.path.clone().into_os_string();
+            rotated.push(".1");
+            let rotated = PathBuf::from(rotated);
+            let rotated_identity = fs::metadata(&rotated)
+                .ok()
+                .and_then(|meta| file_identity(&meta));
+            if self.identity.is_some() && rotated_identity == self.identity {
+                lines.extend(read_feed_delta(&rotated, &mut self.offset)?);
+            }
+            self.offset = 0;
+            self.identity = identity;
+        }
+        lines.extend(read_feed_delta(&self.path, &mut self.offset)?);
+        Ok(lines)
+    }
+}
+
+// Streams lines appended to `logs/<log>.log` (`sidecar`, the default, or `app`) as
+// `sidecar_log_line` events until stop_follow_sidecar_log.
+#[tauri::command]
+fn follow_sidecar_log(
+    app: tauri::AppHandle,
+    followers: State<'_, LogFollowers>,
+    log: Option<String>,
+) -> Result<bool, AppError> {
+    use notify::Watcher;
+
+    let log = log.unwrap_or_else(|| "sidecar".to_string());
+    if !FOLLOWED_LOGS.contains(&log.as_str()) {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown log {:?}; expected one of {}",
+            log,
+            FOLLOWED_LOGS.join(", ")
+        )));
+    }
+    let mut followers = match followers.0.lock() {
+        Ok(followers) => followers,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    if followers.contains_key(&log) {
+        return Ok(false);
+    }
+    let logs_dir = data_root(&app).join("logs");
+    fs::create_dir_all(&logs_dir)
+        .map_err(|e| AppError::io(format!("Failed to create {}", logs_dir.display()), e))?;
+    let path = resolve_under_data_root(&app, &format!("logs/{}.log", log))?;
+    let file_name = path.file_name().map(|name| name.to_os_string());
+    let mut follower = LogFollower::new(path);
+    let event_log = log.clone();
+    let handler = move |result: notify::Result<notify::Event>| {
+        let event = match result {
+            Ok(event) => event,
+            // Logging here would feed back into app.log while following it.
+            Err(_) => return,
+        };
+        if !event
+            .paths
+            .iter()
+            .any(|changed| changed.file_name().map(|name| name.to_os_string()) == file_name)
+        {
+            return;
+        }
+        if let Ok(lines) = follower.read_new_lines() {
+            for line in lines {
+                let _ = app.emit_all(
+                    "sidecar_log_line",
+                    serde_json::json!({ "log": event_log, "line": line }),
+                );
+            }
+        }
+    };
+    let mut watcher = notify::recommended_watcher(handler)
+        .map_err(|e| AppError::Io(format!("Failed to create log watcher: {}", e)))?;
+    watcher
+        .watch(&logs_dir, notify::RecursiveMode::NonRecursive)
+        .map_err(|e| AppError::Io(format!("Failed to watch {}: {}", logs_dir.display(), e)))?;
+    followers.insert(log, watcher);
+    Ok(true)
+}
+
+#[tauri::command]
+fn stop_follow_sidecar_log(
+    followers: State<'_, LogFollowers>,
+    log: Option<String>,
+) -> Result<bool, AppError> {
+    let log = log.unwrap_or_else(|| "sidecar".to_string());
+    let mut followers = match followers.0.lock() {
+        Ok(followers) => followers,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    Ok(followers.remove(&log).is_some())
+}
+
+// Mirrors `AgentManifest` in communication.py.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    peer_id: String,
+    pgp_fingerprint: String,
+    logit_fingerprint: String,
+    display_name: String,
+    created_at: f64,
+}
+
+const MANIFEST_FIELDS: [(&str, &str); 5] = [
+    ("peerId", "string"),
+    ("pgpFingerprint", "string"),
+    ("logitFingerprint", "string"),
+    ("displayName", "string"),
+    ("createdAt", "number"),
+];
+
+#[derive(Serialize)]
+struct ManifestReport {
+    exists: bool,
+    valid: bool,
+    problems: Vec<String>,
+}
+
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+fn manifest_problems(contents: &str) -> Vec<String> {
+    let value: serde_json::Value = match serde_json::from_str(contents) {
+        Ok(value) => value,
+        Err(err) => return vec![format!("invalid JSON: {}", err)],
+    };
+    let Some(object) = value.as_object() else {
+        return vec![format!(
+            "expected an object, got {}",
+            json_type_name(&value)
+        )];
+    };
+    MANIFEST_FIELDS
+        .iter()
+        .filter_map(|(field, expected)| match object.get(*field) {
+            None => Some(format!("missing field `{}`", field)),
+            Some(found) if json_type_name(found) != *expected => Some(format!(
+                "`{}` should be a {}, got {}",
+                field,
+                expected,
+                json_type_name(found)
+            )),
+            Some(_) => None,
+        })
+        .collect()
+}
+
+fn data_checksum_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+// Checks `contents` (as already read, so a concurrent rewrite can't slip in between) against
+// the `<file>.sha256` the sidecar writes next to it. Files without one aren't checked.
+fn verify_data_checksum(path: &Path, contents: &[u8]) -> Result<(), AppError> {
+    let checksum_path = data_checksum_path(path);
+    if !checksum_path.exists() {
+        return Ok(());
+    }
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let expected = read_checksum(&checksum_path, &file_name)?;
+    if Sha256::digest(contents).as_slice() != expected.as_slice() {
+        return Err(AppError::IntegrityFailed(format!(
+            "{} does not match {}",
+            path.display(),
+            checksum_path.display()
+        )));
+    }
+    Ok(())
+}
+
+// In the `sha256sum` format the sidecar writes.
+fn data_checksum_line(path: &Path, contents: &[u8]) -> String {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    format!("{}  {}", hex::encode(Sha256::digest(contents)), file_name)
+}
+
+#[tauri::command]
+fn get_manifest(app: tauri::AppHandle) -> Result<Option<Manifest>, AppError> {
+    let path = manifest_path(&app);
+    let Some(contents) = read_optional_file_or_gz(path.clone())? else {
+        return Ok(None);
+    };
+    verify_data_checksum(&path, contents.as_bytes())?;
+    let problems = manifest_problems(&contents);
+    if !problems.is_empty() {
+        return Err(AppError::IntegrityFailed(format!(
+            "{} is malformed: {}",
+            path.display(),
+            problems.join("; ")
+        )));
+    }
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| AppError::IntegrityFailed(format!("{} is malformed: {}", path.display(), e)))
+}
+
+#[tauri::command]
+fn validate_manifest(app: tauri::AppHandle) -> Result<ManifestReport, AppError> {
+    let path = manifest_path(&app);
+    let Some(contents) = read_optional_file_or_gz(path.clone())? else {
+        return Ok(ManifestReport {
+            exists: false,
+            valid: false,
+            problems: vec!["manifest.json does not exist".to_string()],
+        });
+    };
+    let mut problems = manifest_problems(&contents);
+    if let Err(err) = verify_data_checksum(&path, contents.as_bytes()) {
+        problems.push(err.to_string());
+    }
+    Ok(ManifestReport {
+        exists: true,
+        valid: problems.is_empty(),
+        problems,
+    })
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum DataFileStatus {
+    Ok,
+    // Ends mid-value: the writer was interrupted.
+    Truncated,
+    Unparseable,
+    ChecksumMismatch,
+    Unreadable,
+}
+
+#[derive(Serialize)]
+struct DataFileReport {
+    // Relative to the data root.
+    path: String,
+    status: DataFileStatus,
+    error: Option<String>,
+}
+
+fn scan_json_file(path: &Path) -> (DataFileStatus, Option<String>) {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) => return (DataFileStatus::Unreadable, Some(err.to_string())),
+    };
+    if let Err(err) = serde_json::from_slice::<serde_json::Value>(&contents) {
+        let status = if err.is_eof() {
+            DataFileStatus::Truncated
+        } else {
+            DataFileStatus::Unparseable
+        };
+        return (status, Some(err.to_string()));
+    }
+    match verify_data_checksum(path, &contents) {
+        Ok(()) => (DataFileStatus::Ok, None),
+        Err(err) => (DataFileStatus::ChecksumMismatch, Some(err.to_string())),
+    }
+}
+
+// Streams line by line so a large feed isn't read into memory; stops at the first bad line.
+fn scan_jsonl_file(path: &Path) -> (DataFileStatus, Option<String>) {
+    use std::io::BufRead;
+
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => return (DataFileStatus::Unreadable, Some(err.to_string())),
+    };
+    let mut reader = std::io::BufReader::new(file);
+    let mut line = Vec::new();
+    let mut line_number = 0usize;
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => return (DataFileStatus::Ok, None),
+            Ok(_) => {}
+            Err(err) => return (DataFileStatus::Unreadable, Some(err.to_string())),
+        }
+        line_number += 1;
+        let complete = line.ends_with(b"\n");
+        // Same leniency as the feed readers: a BOM is tolerated, a blank line skipped.
+        let content = line.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&line);
+        if content.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if let Err(err) = serde_json::from_slice::<serde_json::Value>(content) {
+            // A final line without its newline is a write that never finished.
+            let status = if !complete {
+                DataFileStatus::Truncated
+            } else {
+                DataFileStatus::Unparseable
+            };
+            return (status, Some(format!("line {}: {}", line_number, err)));
+        }
+    }
+}
+
+// Read-only health report over every `.json` / `.jsonl` file under `vault/` and `mesh/`, for
+// attaching to bug reports.
+#[tauri::command]
+async fn scan_data_integrity(app: tauri::AppHandle) -> Result<Vec<DataFileReport>, AppError> {
+    let root = data_root(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut files = Vec::new();
+        for dir in MIGRATED_DIRS {
+            let dir = root.join(dir);
+            if dir.is_dir() {
+                collect_tree(&dir, &root, &mut files)?;
+            }
+        }
+        files.sort();
+        let reports = files
+            .into_iter()
+            .filter_map(|(relative, _)| {
+                let path = root.join(&relative);
+                let (status, error) = match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("json") => scan_json_file(&path),
+                    Some("jsonl") => scan_jsonl_file(&path),
+                    _ => return None,
+                };
+                Some(DataFileReport {
+                    path: relative.to_string_lossy().to_string(),
+                    status,
+                    error,
+                })
+            })
+            .collect();
+        Ok::<_, AppError>(reports)
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Integrity scan task failed: {}", e)))?
+}
+
+// Every command that takes a path from the UI goes through here. `relative` must be a plain
+// relative path (no `..`, no root or drive prefix), and once symlinks are resolved the result
+// must still be inside the data root. Components that don't exist yet are allowed, so it also
+// works for files about to be created; a dangling symlink is not.
+fn resolve_under_data_root(app: &tauri::AppHandle, relative: &str) -> Result<PathBuf, AppError> {
+    resolve_under(&data_root(app), relative)
+}
+
+fn resolve_under(root: &Path, relative: &str) -> Result<PathBuf, AppError> {
+    use std::path::Component;
+
+    let relative_path = Path::new(relative);
+    let plain = relative_path
+        .components()
+        .all(|part| matches!(part, Component::Normal(_) | Component::CurDir));
+    if relative.is_empty() || !plain {
+        return Err(AppError::InvalidPath(format!(
+            "{:?} is not a relative path inside the data root",
+            relative
+        )));
+    }
+    let root = root
+        .canonicalize()
+        .map_err(|e| AppError::io(format!("Failed to resolve {}", root.display()), e))?;
+
+    // Canonicalize the longest part that exists and append the rest as given.
+    let joined = root.join(relative_path);
+    let mut existing = joined.as_path();
+    let mut missing = Vec::new();
+    while fs::symlink_metadata(existing).is_err() {
+        let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+            break;
+        };
+        missing.push(name.to_os_string());
+        existing = parent;
+    }
+    let mut resolved = existing
+        .canonicalize()
+        .map_err(|e| AppError::io(format!("Failed to resolve {}", relative), e))?;
+    resolved.extend(missing.iter().rev());
+    if !resolved.starts_with(&root) {
+        return Err(AppError::InvalidPath(format!(
+            "{} points outside the data root",
+            relative
+        )));
+    }
+    Ok(resolved)
+}
+
+// Hex digest of a file under the data root, e.g. a vault file to compare with a peer's copy.
+#[tauri::command]
+async fn hash_data_file(
+    app: tauri::AppHandle,
+    relative_path: String,
+    algo: Option<HashAlgorithm>,
+) -> Result<String, AppError> {
+    let algo = algo.unwrap_or(HashAlgorithm::Sha256);
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = resolve_under_data_root(&app, &relative_path)?;
+        if !path.is_file() {
+            return Err(AppError::InvalidInput(format!(
+                "{} is not a file",
+                relative_path
+            )));
+        }
+        hash_file(&path, algo).map(hex::encode)
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Hashing task failed: {}", e)))?
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+struct DiagnosticsConfig {
+    // JSON fields whose name contains any of these (case-insensitive) are blanked out.
+    redact_fields: Vec<String>,
+    feed_lines: usize,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            redact_fields: ["address", "key", "secret", "token", "password", "signature"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            feed_lines: 200,
+        }
+    }
+}
+
+fn diagnostics_config_path(data_root: &Path) -> PathBuf {
+    data_root.join("mesh").join("diagnostics_config.json")
+}
+
+fn load_diagnostics_config(data_root: &Path) -> DiagnosticsConfig {
+    let path = diagnostics_config_path(data_root);
+    match read_optional_file(path.clone()) {
+        Ok(Some(contents)) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            warn!("Invalid {}: {e}; using defaults", path.display());
+            DiagnosticsConfig::default()
+        }),
+        Ok(None) => DiagnosticsConfig::default(),
+        Err(err) => {
+            warn!("{}; using default diagnostics config", err);
+            DiagnosticsConfig::default()
+        }
+    }
+}
+
+#[tauri::command]
+fn get_diagnostics_config(app: tauri::AppHandle) -> Result<DiagnosticsConfig, AppError> {
+    Ok(load_diagnostics_config(&data_root(&app)))
+}
+
+#[tauri::command]
+fn set_diagnostics_config(
+    app: tauri::AppHandle,
+    config: DiagnosticsConfig,
+) -> Result<(), AppError> {
+    ensure_writable(&app)?;
+    let payload = serde_json::to_string_pretty(&config).map_err(|e| {
+        AppError::InvalidInput(format!("Failed to serialize diagnostics config: {e}"))
+    })?;
+    write_json_atomic(&diagnostics_config_path(&data_root(&app)), &payload)
+}
+
+fn redact_json(value: &mut serde_json::Value, denylist: &[String]) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                let name = name.to_lowercase();
+                if denylist
+                    .iter()
+                    .any(|denied| name.contains(&denied.to_lowercase()))
+                {
+                    *field = serde_json::json!("[redacted]");
+                } else {
+                    redact_json(field, denylist);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                redact_json(item, denylist);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Lines that aren't JSON can't be redacted field by field, so they're left out entirely.
+fn redact_json_lines(lines: &[String], denylist: &[String]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(mut value) => {
+                redact_json(&mut value, denylist);
+                out.push_str(&value.to_string());
+            }
+            Err(_) => out.push_str("\"[redacted: not JSON]\""),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct DiagnosticsBundle {
+    path: String,
+    files: Vec<String>,
+    bytes: u64,
+}
+
+fn write_zip(path: &Path, entries: &[(String, Vec<u8>)]) -> Result<(), AppError> {
+    let zip_error = |e: zip::result::ZipError| {
+        AppError::Io(format!("Failed to write {}: {}", path.display(), e))
+    };
+    let file = File::create(path)
+        .map_err(|e| AppError::io(format!("Failed to create {}", path.display()), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, contents) in entries {
+        zip.start_file(name.as_str(), options).map_err(zip_error)?;
+        zip.write_all(contents)
+            .map_err(|e| AppError::io(format!("Failed to write {}", path.display()), e))?;
+    }
+    let file = zip.finish().map_err(zip_error)?;
+    file.sync_all()
+        .map_err(|e| AppError::io(format!("Failed to sync {}", path.display()), e))
+}
+
+fn export_diagnostics_blocking(
+    app: &tauri::AppHandle,
+    dest: &Path,
+) -> Result<DiagnosticsBundle, AppError> {
+    let root = data_root(app);
+    let config = load_diagnostics_config(&root);
+    let denylist = &config.redact_fields;
+    let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+
+    let logs = root.join("logs");
+    // app.log is JSON lines and gets redacted like the data files; the sidecar's own output is
+    // free text and goes in as written.
+    let app_log = tail_lines(&logs.join("app.log"), DIAGNOSTICS_LOG_LINES)?;
+    entries.push((
+        "logs/app.log".to_string(),
+        redact_json_lines(&app_log, denylist).into_bytes(),
+    ));
+    let sidecar_log = tail_lines(&logs.join("sidecar.log"), DIAGNOSTICS_LOG_LINES)?;
+    entries.push((
+        "logs/sidecar.log".to_string(),
+        sidecar_log.join("\n").into_bytes(),
+    ));
+
+    let mesh = root.join("mesh");
+    let json_files = [
+        ("mesh/resource_state.json", mesh.join("resource_state.json")),
+        ("mesh/peers.json", mesh.join("peers.json")),
+        ("vault/manifest.json", manifest_path(app)),
+    ];
+    for (name, path) in json_files {
+        let Some(contents) = read_optional_file_or_gz(path)? else {
+            continue;
+        };
+        let contents = match serde_json::from_str::<serde_json::Value>(&contents) {
+            Ok(mut value) => {
+                redact_json(&mut value, denylist);
+                serde_json::to_string_pretty(&value).unwrap_or_default()
+            }
+            Err(err) => format!("[redacted: not JSON ({})]", err),
+        };
+        entries.push((name.to_string(), contents.into_bytes()));
+    }
+
+    let feed = tail_lines(&feed_path(app), config.feed_lines)?;
+    entries.push((
+        "mesh/agent_feed.jsonl".to_string(),
+        redact_json_lines(&feed, denylist).into_bytes(),
+    ));
+
+    // The checksum that shipped with the sidecar, never the binary itself.
+    let (bundled_version, checksum) = bundled_sidecar_version(app, DEFAULT_SIDECAR_NAME);
+    if let Ok((_, checksum_path)) = resolve_sidecar_paths(app, DEFAULT_SIDECAR_NAME) {
+        if let Ok(contents) = fs::read(&checksum_path) {
+            let file_name = checksum_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            entries.push((format!("sidecar/{}", file_name), contents));
+        }
+    }
+    let checksum = checksum
+        .map(|(algorithm, digest)| serde_json::json!({ "algorithm": algorithm, "digest": digest }));
+    let version = serde_json::json!({
+        "app_version": app.package_info().version.to_string(),
+        "sidecar_version": bundled_version,
+        "sidecar_checksum": checksum,
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "exported_at": chrono::Utc::now().to_rfc3339(),
+    });
+    entries.push((
+        "version.json".to_string(),
+        serde_json::to_string_pretty(&version)
+            .unwrap_or_default()
+            .into_bytes(),
+    ));
+
+    // Written next to `dest` and renamed into place, so a failed export leaves nothing behind.
+    let tmp_path = unique_tmp_path(dest);
+    let written = write_zip(&tmp_path, &entries).and_then(|()| {
+        fs::rename(&tmp_path, dest)
+            .map_err(|e| AppError::io(format!("Failed to write {}", dest.display()), e))
+    });
+    if let Err(err) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err);
+    }
+
+    let bytes = fs::metadata(dest).map(|meta| meta.len()).unwrap_or(0);
+    Ok(DiagnosticsBundle {
+        path: dest.to_string_lossy().to_string(),
+        files: entries.into_iter().map(|(name, _)| name).collect(),
+        bytes,
+    })
+}
+
+// Zips logs, mesh state, the manifest, version info and the tail of the feed into `dest` for
+// bug reports, with fields named in the diagnostics config redacted.
+#[tauri::command]
+async fn export_diagnostics(
+    app: tauri::AppHandle,
+    dest: String,
+) -> Result<DiagnosticsBundle, AppError> {
+    let dest = PathBuf::from(dest);
+    if !dest.is_absolute() {
+        return Err(AppError::InvalidInput(format!(
+            "{} is not an absolute path",
+            dest.display()
+        )));
+    }
+    tauri::async_runtime::spawn_blocking(move || export_diagnostics_blocking(&app, &dest))
+        .await
+        .map_err(|e| AppError::Io(format!("Diagnostics export task failed: {}", e)))?
+}
+
+// The sidecar may store large manifests as `manifest.json.gz`; readers take either, and a
+// `manifest.json.sha256` covers the decompressed JSON.
+fn manifest_path(app: &tauri::AppHandle) -> PathBuf {
+    data_root(app).join("vault").join("manifest.json")
+}
+
+// Index 0 is the most recent backup. The manifest being replaced is itself backed up (or, if it
+// doesn't parse, the last good copy is), so a restore can be undone with another restore.
+#[tauri::command]
+fn restore_manifest_backup(
+    app: tauri::AppHandle,
+    snapshot: State<'_, ManifestSnapshot>,
+    index: usize,
+) -> Result<(), AppError> {
+    ensure_writable(&app)?;
+    if index >= MANIFEST_BACKUP_KEEP {
+        return Err(AppError::InvalidInput(format!(
+            "Backup index {} out of range (0..{})",
+            index, MANIFEST_BACKUP_KEEP
+        )));
+    }
+    let path = manifest_path(&app);
+    let backup = backup_path(&path, index);
+    let contents = fs::read_to_string(&backup)
+        .map_err(|e| AppError::io(format!("Failed to read {}", backup.display()), e))?;
+    serde_json::from_str::<serde_json::Value>(&contents).map_err(|e| {
+        AppError::IntegrityFailed(format!(
+            "Backup {} is not valid JSON: {}",
+            backup.display(),
+            e
+        ))
+    })?;
+    let payload = contents.trim_end();
+    let mut writes = vec![(path.clone(), payload.to_string())];
+    // An existing companion checksum has to change together with the manifest, or the next
+    // read rejects it. The written file is the payload plus a trailing newline.
+    let checksum_path = data_checksum_path(&path);
+    if checksum_path.exists() {
+        let line = data_checksum_line(&path, format!("{payload}\n").as_bytes());
+        writes.push((checksum_path, line));
+    }
+    snapshot.back_up_current(&path)?;
+    write_json_atomic_batch(writes, Some(&write_journal_path(&data_root(&app))))?;
+    // Already backed up above; the watcher must not back up the replaced copy a second time.
+    snapshot.set(format!("{payload}\n"));
+    info!("Restored manifest from {}", backup.display());
+    Ok(())
+}
+
+#[tauri::command]
+fn get_peers(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
+    let path = data_root(&app).join("mesh").join("peers.json");
+    let contents = read_optional_file(path.clone())?;
+    if let Some(contents) = &contents {
+        verify_data_checksum(&path, contents.as_bytes())?;
+    }
+    Ok(contents)
+}
+
+// Mirrors `PeerRecord` in discovery.py; peers.json is `{ "peers": [...] }`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Peer {
+    peer_id: String,
+    address: String,
+    #[serde(default)]
+    protocols: Vec<String>,
+    #[serde(default)]
+    reputation_score: f64,
+    #[serde(default)]
+    uptime: f64,
+    #[serde(default)]
+    last_verified: f64,
+    #[serde(default)]
+    last_seen: f64,
+}
+
+#[derive(Deserialize)]
+struct PeerList {
+    #[serde(default)]
+    peers: Vec<Peer>,
+}
+
+#[derive(Serialize)]
+struct PeerPing {
+    peer_id: String,
+    probe: HealthProbe,
+    latency_ms: Option<u64>,
+}
+
+fn read_peers(app: &tauri::AppHandle) -> Result<Vec<Peer>, AppError> {
+    let path = data_root(app).join("mesh").join("peers.json");
+    let Some(contents) = read_optional_file(path.clone())? else {
+        return Ok(Vec::new());
+    };
+    verify_data_checksum(&path, contents.as_bytes())?;
+    serde_json::from_str::<PeerList>(&contents)
+        .map(|list| list.peers)
+        .map_err(|e| AppError::IntegrityFailed(format!("{} is malformed: {}", path.display(), e)))
+}
+
+// Splits `ws://host:port/path` into its parts. TLS (`wss://`) isn't supported by the probe.
+fn parse_ws_address(address: &str) -> Result<(String, u16, String), AppError> {
+    parse_address(address, "ws://")
+}
+
+// `<scheme>host:port[/path]` into (host, port, path); the port is required.
+fn parse_address(address: &str, scheme: &str) -> Result<(String, u16, String), AppError> {
+    let rest = address
+        .strip_prefix(scheme)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unsupported address: {}", address)))?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = authority
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        .ok_or_else(|| AppError::InvalidInput(format!("Address has no port: {}", address)))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+#[tauri::command]
+fn get_peers_typed(app: tauri::AppHandle) -> Result<Vec<Peer>, AppError> {
+    read_peers(&app)
+}
+
+#[tauri::command]
+async fn ping_peer(app: tauri::AppHandle, id: String) -> Result<PeerPing, AppError> {
+    let peer = read_peers(&app)?
+        .into_iter()
+        .find(|peer| peer.peer_id == id)
+        .ok_or_else(|| AppError::NotFound(format!("Unknown peer: {}", id)))?;
+    let (host, port, path) = parse_ws_address(&peer.address)?;
+    let started = Instant::now();
+    let probe = probe_websocket(&host, port, &path, HEALTH_CHECK_TIMEOUT).await;
+    let latency_ms = probe
+        .is_healthy()
+        .then(|| started.elapsed().as_millis() as u64);
+    Ok(PeerPing {
+        peer_id: peer.peer_id,
+        probe,
+        latency_ms,
+    })
+}
+
+#[tauri::command]
+fn get_feed(app: tauri::AppHandle, limit: usize) -> Result<Vec<String>, AppError> {
+    tail_lines(&feed_path(&app), limit)
+}
+
+#[derive(Serialize)]
+struct FeedEntries {
+    entries: Vec<serde_json::Value>,
+    // Lines among the last `limit` that aren't valid JSON.
+    skipped: usize,
+}
+
+// get_feed with each line parsed; the string form stays for raw access.
+#[tauri::command]
+fn get_feed_json(app: tauri::AppHandle, limit: usize) -> Result<FeedEntries, AppError> {
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for line in tail_lines(&feed_path(&app), limit)? {
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok(FeedEntries { entries, skipped })
+}
+
+// All set criteria must match. `field`/`equals` compare a top-level key of the entry, and
+// `since`/`until` bound its `timestamp` (epoch seconds, inclusive).
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+struct FeedFilter {
+    contains: Option<String>,
+    field: Option<String>,
+    equals: Option<serde_json::Value>,
+    since: Option<f64>,
+    until: Option<f64>,
+}
+
+impl FeedFilter {
+    fn needs_json(&self) -> bool {
+        self.field.is_some() || self.since.is_some() || self.until.is_some()
+    }
+
+    fn matches(&self, line: &str) -> bool {
+        if let Some(needle) = &self.contains {
+            if !line.contains(needle.as_str()) {
+                return false;
+            }
+        }
+        if !self.needs_json() {
+            return true;
+        }
+        // Malformed lines simply don't match a structured filter.
+        let Ok(entry) = serde_json::from_str::<serde_json::Value>(line) else {
+            return false;
+        };
+        if let Some(field) = &self.field {
+            match (entry.get(field), &self.equals) {
+                (None, _) => return false,
+                (Some(value), Some(expected)) if value != expected => return false,
+                _ => {}
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let Some(timestamp) = entry.get("timestamp").and_then(feed_timestamp) else {
+                return false;
+            };
+            if self.since.is_some_and(|since| timestamp < since)
+                || self.until.is_some_and(|until| timestamp > until)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn feed_timestamp(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(number) => number.as_f64(),
+        serde_json::Value::String(text) => chrono::DateTime::parse_from_rfc3339(text)
+            .ok()
+            .map(|time| time.timestamp_millis() as f64 / 1000.0),
+        _ => None,
+    }
+}
+
+fn feed_path(app: &tauri::AppHandle) -> PathBuf {
+    data_root(app).join("mesh").join("agent_feed.jsonl")
+}
+
+// Reads complete lines appended since `offset` and advances it past them. A trailing partial
+// line is left for the next call; a file shorter than `offset` was truncated and is re-read.
+fn read_feed_delta(path: &Path, offset: &mut u64) -> Result<Vec<String>, AppError> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(AppError::io(
+                format!("Failed to open {}", path.display()),
+                e,
+            ))
+        }
+    };
+    let len = file
+        .metadata()
+        .map_err(|e| AppError::io(format!("Failed to stat {}", path.display()), e))?
+        .len();
+    if len < *offset {
+        *offset = 0;
+    }
+    if len == *offset {
+        return Ok(Vec::new());
+    }
+    file.seek(SeekFrom::Start(*offset))
+        .map_err(|e| AppError::io(format!("Failed to seek {}", path.display()), e))?;
+    let mut buffer = Vec::new();
+    file.take(len - *offset)
+        .read_to_end(&mut buffer)
+        .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+    let Some(end) = buffer.iter().rposition(|byte| *byte == b'\n') else {
+        return Ok(Vec::new());
+    };
+    *offset += end as u64 + 1;
+    Ok(String::from_utf8_lossy(&buffer[..end])
+        .split('\n')
+        .filter_map(clean_feed_line)
+        .map(|line| line.to_string())
+        .collect())
+}
+
+// Walks the feed backwards and stops at the `limit`th match, so a narrow filter over a large
+// feed only reads as far back as it has to.
+#[tauri::command]
+async fn get_feed_filtered(
+    app: tauri::AppHandle,
+    limit: usize,
+    filter: FeedFilter,
+) -> Result<Vec<String>, AppError> {
+    let path = feed_path(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut matches = Vec::new();
+        if limit == 0 {
+            return Ok(matches);
+        }
+        for_each_line_rev(&path, |line| {
+            if filter.matches(line) {
+                matches.push(line.to_string());
+            }
+            matches.len() < limit
+        })?;
+        Ok(matches)
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Feed filter task failed: {}", e)))?
+}
+
+#[derive(Serialize)]
+struct FeedStats {
+    entry_count: u64,
+    byte_size: u64,
+    oldest_ts: Option<f64>,
+    newest_ts: Option<f64>,
+}
+
+fn line_timestamp(line: &str) -> Option<f64> {
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()?
+        .get("timestamp")
+        .and_then(feed_timestamp)
+}
+
+// Streams the file once, counting non-blank lines and keeping only the first one around.
+#[tauri::command]
+fn feed_stats(app: tauri::AppHandle) -> Result<FeedStats, AppError> {
+    let path = feed_path(&app);
+    if !path.exists() {
+        return Ok(FeedStats {
+            entry_count: 0,
+            byte_size: 0,
+            oldest_ts: None,
+            newest_ts: None,
+        });
+    }
+    let mut entry_count = 0u64;
+    let mut byte_size = 0u64;
+    let mut first_line = Vec::new();
+    let mut line_has_content = false;
+    stream_file(&path, |chunk| {
+        byte_size += chunk.len() as u64;
+        for &byte in chunk {
+            if byte == b'\n' {
+                if line_has_content {
+                    entry_count += 1;
+                } else if entry_count == 0 {
+                    first_line.clear();
+                }
+                line_has_content = false;
+                continue;
+            }
+            if entry_count == 0 {
+                first_line.push(byte);
+            }
+            if !byte.is_ascii_whitespace() {
+                line_has_content = true;
+            }
+        }
+    })?;
+    if line_has_content {
+        entry_count += 1;
+    }
+    let newest_ts = tail_lines(&path, 1)?
+        .last()
+        .and_then(|line| line_timestamp(line));
+    let first_line = String::from_utf8_lossy(&first_line);
+    Ok(FeedStats {
+        entry_count,
+        byte_size,
+        oldest_ts: clean_feed_line(&first_line).and_then(line_timestamp),
+        newest_ts,
+    })
+}
+
+#[derive(Serialize)]
+struct FeedCompaction {
+    kept: usize,
+    removed: usize,
+    archive_path: Option<String>,
+}
+
+fn append_feed_archive(path: &Path, lines: &[&[u8]]) -> Result<(), AppError> {
+    use flate2::write::GzEncoder;
+
+    // Appending another gzip member keeps earlier archives from the same day readable.
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| AppError::io(format!("Failed to open {}", path.display()), e))?;
+    let mut encoder = GzEncoder::new(file, flate2::Compression::default());
+    for line in lines {
+        encoder
+            .write_all(line)
+            .and_then(|()| encoder.write_all(b"\n"))
+            .map_err(|e| AppError::io(format!("Failed to write {}", path.display()), e))?;
+    }
+    encoder
+        .finish()
+        .and_then(|file| file.sync_all())
+        .map_err(|e| AppError::io(format!("Failed to finish {}", path.display()), e))
+}
+
+fn is_blank_line(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+#[derive(Clone, Copy)]
+enum FeedKeep {
+    // The newest this many entries.
+    Entries(usize),
+    // The newest lines that fit in this many bytes.
+    Bytes(u64),
+}
+
+// Where the kept part of the feed starts. Always the start of a line, so what's kept is a
+// byte-for-byte suffix of the old feed and offsets into it stay meaningful.
+fn feed_cut(contents: &[u8], keep: FeedKeep) -> usize {
+    match keep {
+        FeedKeep::Entries(max_entries) => {
+            // Walk back line by line; the cut goes right after the first line not kept.
+            let mut kept = 0;
+            let mut line_end = contents.len();
+            loop {
+                let line_start = contents[..line_end]
+                    .iter()
+                    .rposition(|byte| *byte == b'\n')
+                    .map_or(0, |newline| newline + 1);
+                if !is_blank_line(&contents[line_start..line_end]) {
+                    if kept == max_entries {
+                        return (line_end + 1).min(contents.len());
+                    }
+                    kept += 1;
+                }
+                if line_start == 0 {
+                    return 0;
+                }
+                line_end = line_start - 1;
+            }
+        }
+        FeedKeep::Bytes(budget) => {
+            let Some(from) = contents.len().checked_sub(budget as usize) else {
+                return 0;
+            };
+            if from == 0 || contents[from - 1] == b'\n' {
+                return from;
+            }
+            contents[from..]
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .map_or(contents.len(), |end| from + end + 1)
+        }
+    }
+}
+
+// Holds FeedWriter so our own appends wait. What the sidecar appends to the old file while we
+// rewrite it is copied over after the rename; only a sidecar that keeps the old file open past
+// that point writes to a file nobody reads, which is why nothing triggers this by default.
+fn compact_feed_file(
+    app: &tauri::AppHandle,
+    keep: FeedKeep,
+    archive: bool,
+) -> Result<FeedCompaction, AppError> {
+    let writer = app.state::<FeedWriter>();
+    let mut rewrites = match writer.0.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let path = feed_path(app);
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Ok(FeedCompaction {
+                kept: 0,
+                removed: 0,
+                archive_path: None,
+            })
+        }
+        Err(e) => {
+            return Err(AppError::io(
+                format!("Failed to open {}", path.display()),
+                e,
+            ))
+        }
+    };
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)
+        .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+    let cut = feed_cut(&contents, keep);
+    let (trimmed, kept) = contents.split_at(cut);
+    let count = |bytes: &[u8]| {
+        bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !is_blank_line(line))
+            .count()
+    };
+    if cut == 0 {
+        return Ok(FeedCompaction {
+            kept: count(kept),
+            removed: 0,
+            archive_path: None,
+        });
+    }
+    let trimmed: Vec<&[u8]> = trimmed
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !is_blank_line(line))
+        .collect();
+
+    let archive_path = if archive {
+        let name = format!(
+            "agent_feed.{}.jsonl.gz",
+            chrono::Utc::now().format("%Y-%m-%d")
+        );
+        let archive_path = path.with_file_name(name);
+        append_feed_archive(&archive_path, &trimmed)?;
+        Some(archive_path.to_string_lossy().to_string())
+    } else {
+        None
+    };
+    write_atomic(&path, kept)?;
+    rewrites.removed_bytes += cut as u64;
+    // The handle still points at the old file; carry over what was appended to it since the read.
+    let mut late = Vec::new();
+    file.read_to_end(&mut late)
+        .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+    if !late.is_empty() {
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(&late))
+            .map_err(|e| AppError::io(format!("Failed to append to {}", path.display()), e))?;
+    }
+    let kept = count(kept) + count(&late[..]);
+    info!("Compacted feed: kept {}, removed {}", kept, trimmed.len());
+    Ok(FeedCompaction {
+        kept,
+        removed: trimmed.len(),
+        archive_path,
+    })
+}
+
+#[tauri::command]
+async fn compact_feed(
+    app: tauri::AppHandle,
+    max_entries: usize,
+    archive: Option<bool>,
+) -> Result<FeedCompaction, AppError> {
+    ensure_writable(&app)?;
+    if max_entries == 0 {
+        return Err(AppError::InvalidInput(
+            "max_entries must be at least 1".to_string(),
+        ));
+    }
+    let archive = archive.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        compact_feed_file(&app, FeedKeep::Entries(max_entries), archive)
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Feed compaction task failed: {}", e)))?
+}
+
+fn feed_max_bytes() -> u64 {
+    std::env::var("PROJECT_DAWN_FEED_MAX_BYTES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_FEED_MAX_BYTES)
+}
+
+async fn compact_feed_if_oversized(app: &tauri::AppHandle) {
+    let max_bytes = feed_max_bytes();
+    if max_bytes == 0 {
+        return;
+    }
+    let size = fs::metadata(feed_path(app))
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+    if size <= max_bytes {
+        return;
+    }
+    // Down to half the cap, so the next compaction is a while off.
+    let app = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        compact_feed_file(&app, FeedKeep::Bytes(max_bytes / 2), true)
+    })
+    .await;
+    match result {
+        Ok(Ok(_)) => {}
+        Ok(Err(err)) => error!("Feed compaction failed: {}", err),
+        Err(err) => error!("Feed compaction task failed: {}", err),
+    }
+}
+
+// Serializes appends from this process so two writers never interleave partial lines.
+struct FeedWriter(std::sync::Mutex<FeedRewrites>);
+
+// Bytes this process has cut from the front of the feed (compaction, reset). A reader holding
+// an offset into the feed subtracts what was cut since it last looked instead of starting over.
+#[derive(Default)]
+struct FeedRewrites {
+    removed_bytes: u64,
+}
+
+fn append_feed_line(app: &tauri::AppHandle, entry: &serde_json::Value) -> Result<(), AppError> {
+    let writer = app.state::<FeedWriter>();
+    let _guard = match writer.0.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let path = feed_path(app);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| AppError::io(format!("Failed to create {}", parent.display()), e))?;
+    }
+    let mut line = entry.to_string();
+    line.push('\n');
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| AppError::io(format!("Failed to open {}", path.display()), e))?;
+    // A single write of the whole line with O_APPEND keeps it contiguous even next to the
+    // sidecar's own appends.
+    file.write_all(line.as_bytes())
+        .map_err(|e| AppError::io(format!("Failed to append to {}", path.display()), e))?;
+    file.sync_all()
+        .map_err(|e| AppError::io(format!("Failed to sync {}", path.display()), e))
+}
+
+const MESH_RESET_TARGETS: [&str; 3] = ["peers", "feed", "resource"];
+
+#[derive(Serialize)]
+struct MeshReset {
+    target: String,
+    path: String,
+    // The previous contents, kept as `<file>.bak`.
+    backup: String,
+    bytes_removed: u64,
+}
+
+// Backs the file up to `<file>.bak`, then resets it: peers.json to an empty peer list, the feed
+// to an empty file and resource_state.json removed (the monitor writes a new one). Missing
+// files are skipped.
+fn reset_mesh_file(app: &tauri::AppHandle, target: &str) -> Result<Option<MeshReset>, AppError> {
+    let root = data_root(app);
+    let path = match target {
+        "peers" => root.join("mesh").join("peers.json"),
+        "feed" => feed_path(app),
+        _ => root.join("mesh").join("resource_state.json"),
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes_removed = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+    match target {
+        "peers" => {
+            rotate_backups(&path, 1)?;
+            let payload = serde_json::json!({ "peers": [] }).to_string();
+            let checksum_path = data_checksum_path(&path);
+            let checksum = checksum_path
+                .exists()
+                .then(|| data_checksum_line(&path, format!("{payload}\n").as_bytes()));
+            let mut writes = vec![(path.clone(), payload)];
+            writes.extend(checksum.map(|line| (checksum_path, line)));
+            write_json_atomic_batch(writes, Some(&write_journal_path(&root)))?;
+        }
+        "feed" => {
+            // Under the writer lock so no append lands between the backup and the truncate.
+            let writer = app.state::<FeedWriter>();
+            let mut rewrites = match writer.0.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            rotate_backups(&path, 1)?;
+            File::create(&path)
+                .and_then(|file| file.sync_all())
+                .map_err(|e| AppError::io(format!("Failed to truncate {}", path.display()), e))?;
+            rewrites.removed_bytes += bytes_removed;
+        }
+        _ => {
+            rotate_backups(&path, 1)?;
+            fs::remove_file(&path)
+                .map_err(|e| AppError::io(format!("Failed to remove {}", path.display()), e))?;
+        }
+    }
+    Ok(Some(MeshReset {
+        target: target.to_string(),
+        path: path.to_string_lossy().to_string(),
+        backup: backup_path(&path, 0).to_string_lossy().to_string(),
+        bytes_removed,
+    }))
+}
+
+// For recovering from corrupted mesh state. Only with every sidecar stopped, since the sidecar
+// owns these files while it runs. Returns what was actually reset.
+#[tauri::command]
+async fn reset_mesh_state(
+    registry: State<'_, SidecarRegistry>,
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    app: tauri::AppHandle,
+    which: Vec<String>,
+) -> Result<Vec<MeshReset>, AppError> {
+    ensure_writable(&app)?;
+    if which.is_empty() {
+        return Err(AppError::InvalidInput("Nothing to reset".to_string()));
+    }
+    if let Some(unknown) = which
+        .iter()
+        .find(|target| !MESH_RESET_TARGETS.contains(&target.as_str()))
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown mesh state {:?} (expected peers, feed or resource)",
+            unknown
+        )));
+    }
+    for sidecar in registry.all() {
+        let guard = sidecar.lock().await;
+        if guard.process.is_some() || guard.external {
+            return Err(AppError::SidecarUnavailable(
+                "Stop the sidecar before resetting mesh state".to_string(),
+            ));
+        }
+    }
+
+    let targets: Vec<&str> = MESH_RESET_TARGETS
+        .into_iter()
+        .filter(|target| which.iter().any(|wanted| wanted == target))
+        .collect();
+    if targets.contains(&"resource") {
+        state.lock().await.last_resource = None;
+    }
+    let task_app = app.clone();
+    let resets = tauri::async_runtime::spawn_blocking(move || {
+        let mut resets = Vec::new();
+        for target in targets {
+            resets.extend(reset_mesh_file(&task_app, target)?);
+        }
+        Ok::<_, AppError>(resets)
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Mesh reset task failed: {}", e)))??;
+    for reset in &resets {
+        info!(
+            "Reset {} ({} bytes, backup at {})",
+            reset.path, reset.bytes_removed, reset.backup
+        );
+    }
+    Ok(resets)
+}
+
+#[tauri::command]
+fn append_feed_entry(app: tauri::AppHandle, entry: String) -> Result<(), AppError> {
+    ensure_writable(&app)?;
+    let mut value: serde_json::Value = serde_json::from_str(&entry)
+        .map_err(|e| AppError::InvalidInput(format!("Feed entry is not valid JSON: {}", e)))?;
+    let Some(object) = value.as_object_mut() else {
+        return Err(AppError::InvalidInput(
+            "Feed entry must be a JSON object".to_string(),
+        ));
+    };
+    if !object.contains_key("timestamp") {
+        let now = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+        object.insert("timestamp".to_string(), serde_json::json!(now));
+    }
+    append_feed_line(&app, &value)
+}
+
+struct FeedWatcher(std::sync::Mutex<Option<notify::RecommendedWatcher>>);
+
+#[tauri::command]
+fn subscribe_feed(
+    app: tauri::AppHandle,
+    watcher: State<'_, FeedWatcher>,
+) -> Result<bool, AppError> {
+    use notify::Watcher;
+
+    let mut watcher = match watcher.0.lock() {
+        Ok(watcher) => watcher,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    if watcher.is_some() {
+        return Ok(false);
+    }
+    let path = feed_path(&app);
+    let mesh_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    fs::create_dir_all(&mesh_dir)
+        .map_err(|e| AppError::io(format!("Failed to create {}", mesh_dir.display()), e))?;
+    // Only entries written after subscribing are streamed; get_feed covers the backlog.
+    let (mut offset, mut removed_bytes) = {
+        let writer = app.state::<FeedWriter>();
+        let rewrites = match writer.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let offset = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        (offset, rewrites.removed_bytes)
+    };
+    let file_name = path.file_name().map(|name| name.to_os_string());
+    // Watch events only nudge the emitter, which batches a burst of writes into one read and
+    // splits it into capped events. It ends when the watcher (and with it `changed`) is dropped.
+    let (changed, mut changes) = tokio::sync::mpsc::unbounded_channel::<()>();
+    tauri::async_runtime::spawn(async move {
+        while changes.recv().await.is_some() {
+            tokio::time::sleep(FEED_EMIT_DEBOUNCE).await;
+            while changes.try_recv().is_ok() {}
+            let lines = {
+                let writer = app.state::<FeedWriter>();
+                let rewrites = match writer.0.lock() {
+                    Ok(guard) => guard,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+                // Compaction keeps a suffix of the feed, so our place in it moved back by
+                // exactly what was cut.
+                offset = offset.saturating_sub(rewrites.removed_bytes - removed_bytes);
+                removed_bytes = rewrites.removed_bytes;
+                read_feed_delta(&path, &mut offset)
+            };
+            let lines = match lines {
+                Ok(lines) => lines,
+                Err(err) => {
+                    warn!("{}", err);
+                    continue;
+                }
+            };
+            let mut chunks = lines.chunks(FEED_EVENT_MAX_LINES).peekable();
+            while let Some(chunk) = chunks.next() {
+                // `truncated` tells the UI more lines from the same burst follow.
+                let _ = app.emit_all(
+                    "feed_append",
+                    serde_json::json!({ "lines": chunk, "truncated": chunks.peek().is_some() }),
+                );
+            }
+        }
+    });
+    let handler = move |result: notify::Result<notify::Event>| {
+        let event = match result {
+            Ok(event) => event,
+            Err(err) => {
+                warn!("Feed watcher error: {}", err);
+                return;
+            }
+        };
+        if !event
+            .paths
+            .iter()
+            .any(|changed| changed.file_name().map(|name| name.to_os_string()) == file_name)
+        {
+            return;
+        }
+        let _ = changed.send(());
+    };
+    let mut new_watcher = notify::recommended_watcher(handler)
+        .map_err(|e| AppError::Io(format!("Failed to create feed watcher: {}", e)))?;
+    new_watcher
+        .watch(&mesh_dir, notify::RecursiveMode::NonRecursive)
+        .map_err(|e| AppError::Io(format!("Failed to watch {}: {}", mesh_dir.display(), e)))?;
+    *watcher = Some(new_watcher);
+    Ok(true)
+}
+
+#[tauri::command]
+fn unsubscribe_feed(watcher: State<'_, FeedWatcher>) -> Result<bool, AppError> {
+    let mut watcher = match watcher.0.lock() {
+        Ok(watcher) => watcher,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // Dropping the watcher stops it.
+    Ok(watcher.take().is_some())
+}
+
+// Keeps the vault/mesh watcher alive for the lifetime of the app.
+struct DataWatcher(std::sync::Mutex<notify::RecommendedWatcher>);
+
+// The last manifest.json seen that parsed as JSON. The sidecar writes the manifest, so the
+// shell can't back it up before a write; instead, when the data watcher sees it change, this
+// copy becomes `manifest.json.bak`.
+#[derive(Clone)]
+struct ManifestSnapshot(Arc<std::sync::Mutex<ManifestSnapshotState>>);
+
+struct ManifestSnapshotState {
+    good: Option<String>,
+    // `good` is already the newest backup, so a following bad write doesn't push it again.
+    backed_up: bool,
+}
+
+impl ManifestSnapshot {
+    fn load(path: &Path) -> Self {
+        let good = fs::read_to_string(path)
+            .ok()
+            .filter(|contents| serde_json::from_str::<serde_json::Value>(contents).is_ok());
+        Self(Arc::new(std::sync::Mutex::new(ManifestSnapshotState {
+            good,
+            backed_up: false,
+        })))
+    }
+
+    fn set(&self, contents: String) {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        state.good = Some(contents);
+        state.backed_up = false;
+    }
+
+    // Called after manifest.json changed on disk. Invalid contents are never adopted, so however
+    // many bad writes follow, the last good manifest is backed up once and stays the newest
+    // backup.
+    fn record_change(&self, path: &Path) {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(err) = state.update(path) {
+            warn!("Failed to back up the previous manifest: {}", err);
+        }
+    }
+
+    // For the shell replacing the manifest itself: makes sure the last good copy, which is the
+    // current file unless that doesn't parse, is the newest backup.
+    fn back_up_current(&self, path: &Path) -> Result<(), AppError> {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        state.update(path)?;
+        state.back_up_good(path)
+    }
+}
+
+impl ManifestSnapshotState {
+    fn update(&mut self, path: &Path) -> Result<(), AppError> {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Ok(());
+        };
+        if self.good.as_deref() == Some(contents.as_str()) {
+            return Ok(());
+        }
+        self.back_up_good(path)?;
+        match serde_json::from_str::<serde_json::Value>(&contents) {
+            Ok(_) => {
+                self.good = Some(contents);
+                self.backed_up = false;
+            }
+            Err(e) => warn!("manifest.json changed to invalid JSON: {}", e),
+        }
+        Ok(())
+    }
+
+    fn back_up_good(&mut self, path: &Path) -> Result<(), AppError> {
+        if self.backed_up {
+            return Ok(());
+        }
+        if let Some(good) = &self.good {
+            push_backup(path, good, MANIFEST_BACKUP_KEEP)?;
+        }
+        self.backed_up = true;
+        Ok(())
+    }
+}
+
+fn change_kind(kind: &notify::EventKind) -> Option<&'static str> {
+    match kind {
+        notify::EventKind::Create(_) => Some("created"),
+        notify::EventKind::Modify(_) => Some("modified"),
+        notify::EventKind::Remove(_) => Some("removed"),
+        notify::EventKind::Access(_) => None,
+        _ => Some("modified"),
+    }
+}
+
+fn start_data_watcher(
+    app: &tauri::AppHandle,
+    manifest: ManifestSnapshot,
+) -> Result<notify::RecommendedWatcher, AppError> {
+    use notify::Watcher;
+
+    let root = data_root(app);
+    let manifest_file = manifest_path(app);
+    let readonly = app.state::<ReadOnly>().0;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<(PathBuf, &'static str)>();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        let event = match result {
+            Ok(event) => event,
+            Err(err) => {
+                warn!("Data watcher error: {}", err);
+                return;
+            }
+        };
+        let Some(kind) = change_kind(&event.kind) else {
+            return;
+        };
+        for path in event.paths {
+            // Temp files from write_json_atomic are noise; only the renamed target matters.
+            if path.extension().map_or(false, |ext| ext == "tmp") {
+                continue;
+            }
+            let _ = tx.send((path, kind));
+        }
+    })
+    .map_err(|e| AppError::Io(format!("Failed to create data watcher: {}", e)))?;
+
+    for dir in ["vault", "mesh"] {
+        let dir = root.join(dir);
+        fs::create_dir_all(&dir)
+            .map_err(|e| AppError::io(format!("Failed to create {}", dir.display()), e))?;
+        watcher
+            .watch(&dir, notify::RecursiveMode::NonRecursive)
+            .map_err(|e| AppError::Io(format!("Failed to watch {}: {}", dir.display(), e)))?;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut pending: HashMap<PathBuf, &'static str> = HashMap::new();
+        while let Some((path, kind)) = rx.recv().await {
+            pending.insert(path, kind);
+            while let Ok(Some((path, kind))) =
+                tokio::time::timeout(FILE_CHANGE_DEBOUNCE, rx.recv()).await
+            {
+                pending.insert(path, kind);
+            }
+            for (path, kind) in pending.drain() {
+                // Judge by the final state: a removal followed by a rename into place is a
+                // modification, and a file that is gone by now was removed.
+                let kind = match (path.exists(), kind) {
+                    (false, _) => "removed",
+                    (true, "removed") => "modified",
+                    (true, kind) => kind,
+                };
+                if path == manifest_file && kind != "removed" && !readonly {
+                    let manifest = manifest.clone();
+                    let path = path.clone();
+                    let _ =
+                        tauri::async_runtime::spawn_blocking(move || manifest.record_change(&path))
+                            .await;
+                }
+                let relative = path
+                    .strip_prefix(&root)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let _ = app.emit_all(
+                    "file_changed",
+                    serde_json::json!({ "path": relative, "kind": kind }),
+                );
+            }
+        }
+    });
+    Ok(watcher)
+}
+
+// resource_state.json from disk. A file that doesn't parse as an object (half-written by a
+// crash) is treated as missing and removed; the monitor writes a fresh one.
+fn read_resource_state_file(app: &tauri::AppHandle) -> Result<Option<serde_json::Value>, AppError> {
+    let path = data_root(app).join("mesh").join("resource_state.json");
+    let Some(contents) = read_optional_file(path.clone())? else {
+        return Ok(None);
+    };
+    match serde_json::from_str::<serde_json::Value>(&contents) {
+        Ok(value) if value.is_object() => return Ok(Some(value)),
+        Ok(_) => warn!("Ignoring {}: not a JSON object", path.display()),
+        Err(err) => warn!("Ignoring corrupt {}: {}", path.display(), err),
+    }
+    if !app.state::<ReadOnly>().0 {
+        if let Err(err) = fs::remove_file(&path) {
+            warn!("Failed to remove {}: {}", path.display(), err);
+        }
+    }
+    Ok(None)
+}
+
+// The in-memory snapshot with a `stale` flag; before the monitor has one, the file's contents.
+#[tauri::command]
+async fn get_resource_state(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    app: tauri::AppHandle,
+) -> Result<Option<String>, AppError> {
+    if let Some(snapshot) = state.lock().await.resource_snapshot() {
+        return Ok(Some(snapshot.to_string()));
+    }
+    let resource = off_runtime(move || read_resource_state_file(&app)).await??;
+    Ok(resource.map(|value| value.to_string()))
+}
+
+#[derive(Serialize)]
+struct SystemOverview {
+    sidecar_running: bool,
+    sidecar_mode: SidecarMode,
+    health: HealthStatus,
+    uptime_secs: Option<u64>,
+    // The latest resource_state snapshot; None before the first sample.
+    resource: Option<serde_json::Value>,
+    data_root: String,
+    version: String,
+}
+
+// Everything the UI polls on refresh in one round-trip. Reads the in-memory state; the resource
+// snapshot only comes from disk before the monitor has written one this run.
+#[tauri::command]
+async fn system_overview(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+    app: tauri::AppHandle,
+) -> Result<SystemOverview, AppError> {
+    let guard = state.lock().await;
+    let sidecar_mode = guard.mode();
+    let health = guard.health.clone();
+    let uptime_secs = guard.uptime_secs();
+    let cached = guard.resource_snapshot();
+    drop(guard);
+
+    let root = data_root(&app);
+    let resource = match cached {
+        Some(resource) => Some(resource),
+        None => {
+            let task_app = app.clone();
+            off_runtime(move || read_resource_state_file(&task_app)).await??
+        }
+    };
+    Ok(SystemOverview {
+        sidecar_running: sidecar_mode != SidecarMode::Stopped,
+        sidecar_mode,
+        health,
+        uptime_secs,
+        resource,
+        data_root: root.to_string_lossy().to_string(),
+        version: app.package_info().version.to_string(),
+    })
+}
+
+#[tauri::command]
+fn get_resource_history(app: tauri::AppHandle, limit: usize) -> Result<Vec<String>, AppError> {
+    let path = data_root(&app).join("mesh").join("resource_history.jsonl");
+    tail_lines(&path, limit.min(RESOURCE_HISTORY_CAP))
+}
+
+// Appends snapshots to resource_history.jsonl and periodically rewrites it down to the
+// newest RESOURCE_HISTORY_CAP lines, so the file stays bounded without a rewrite per sample.
+struct ResourceHistory {
+    path: PathBuf,
+    entries: VecDeque<String>,
+    file_lines: usize,
+}
+
+impl ResourceHistory {
+    // In read-only mode the file is only read; push must not be called either.
+    fn load(path: PathBuf, readonly: bool) -> Self {
+        let entries: VecDeque<String> = tail_lines(&path, RESOURCE_HISTORY_CAP)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        let mut history = Self {
+            path,
+            entries,
+            file_lines: 0,
+        };
+        if readonly {
+            return history;
+        }
+        if let Err(err) = history.compact() {
+            warn!("{}", err);
+        }
+        history
+    }
+
+    fn compact(&mut self) -> Result<(), AppError> {
+        if self.entries.is_empty() {
+            self.file_lines = 0;
+            return Ok(());
+        }
+        let joined = self
+            .entries
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+        write_json_atomic(&self.path, &joined)?;
+        self.file_lines = self.entries.len();
+        Ok(())
+    }
+
+    fn push(&mut self, line: String) -> Result<(), AppError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::io(format!("Failed to create {}", parent.display()), e))?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| AppError::io(format!("Failed to open {}", self.path.display()), e))?;
+        writeln!(file, "{}", line)
+            .map_err(|e| AppError::io(format!("Failed to write {}", self.path.display()), e))?;
+        self.file_lines += 1;
+
+        self.entries.push_back(line);
+        while self.entries.len() > RESOURCE_HISTORY_CAP {
+            self.entries.pop_front();
+        }
+        if self.file_lines >= RESOURCE_HISTORY_CAP + RESOURCE_HISTORY_CAP / 2 {
+            self.compact()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_battery_status() -> (Option<f32>, Option<bool>) {
+    let base = PathBuf::from("/sys/class/power_supply");
+    if !base.exists() {
+        return (None, None);
+    }
+    let read_str = |path: &Path, name: &str| {
+        fs::read_to_string(path.join(name))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    let read_u64 = |path: &Path, name: &str| read_str(path, name).parse::<u64>().ok();
+
+    // Totals across every system battery, kept only while every battery reports the unit
+    // (energy in µWh, charge in µAh; drivers expose one or the other).
+    let mut energy = Some((0u64, 0u64));
+    let mut charge = Some((0u64, 0u64));
+    let mut capacities = Vec::new();
+    let mut batteries = 0;
+    let (mut charging, mut discharging) = (false, false);
+    let (mut ac_seen, mut ac_online) = (false, false);
+
+    if let Ok(entries) = fs::read_dir(base) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let kind = read_str(&path, "type");
+            if kind == "Battery" {
+                // Wireless mice and keyboards show up as batteries too, scoped to their device.
+                if read_str(&path, "scope") == "Device" {
+                    continue;
+                }
+                batteries += 1;
+                let add = |total: Option<(u64, u64)>, now: &str, full: &str| {
+                    let (total_now, total_full) = total?;
+                    Some((
+                        total_now + read_u64(&path, now)?,
+                        total_full + read_u64(&path, full)?,
+                    ))
+                };
+                energy = add(energy, "energy_now", "energy_full");
+                charge = add(charge, "charge_now", "charge_full");
+                if let Some(capacity) = read_u64(&path, "capacity") {
+                    capacities.push(capacity as f32);
+                }
+                match read_str(&path, "status").as_str() {
+                    "Discharging" => discharging = true,
+                    "Charging" | "Full" => charging = true,
+                    _ => {}
+                }
+            } else if kind == "Mains" || kind == "AC" || kind.starts_with("USB") {
+                // USB, USB_C, USB_PD, ... are how USB-C chargers appear.
+                ac_seen = true;
+                ac_online |= read_u64(&path, "online") == Some(1);
+            }
+        }
+    }
+
+    let battery_pct = if batteries == 0 {
+        None
+    } else {
+        [energy, charge]
+            .into_iter()
+            .flatten()
+            .find(|(_, full)| *full > 0)
+            .map(|(now, full)| (now as f64 / full as f64 * 100.0).min(100.0) as f32)
+            .or_else(|| {
+                (!capacities.is_empty())
+                    .then(|| capacities.iter().sum::<f32>() / capacities.len() as f32)
+            })
+    };
+    // Without a recognizable adapter, fall back to what the batteries say.
+    let on_ac = if ac_seen {
+        Some(ac_online)
+    } else if discharging {
+        Some(false)
+    } else if charging {
+        Some(true)
+    } else {
+        None
+    };
+
+    (battery_pct, on_ac)
+}
+
+#[cfg(target_os = "macos")]
+fn read_battery_status() -> (Option<f32>, Option<bool>) {
+    use core_foundation::array::{CFArray, CFArrayRef};
+    use core_foundation::base::{CFType, CFTypeRef, TCFType};
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::number::CFNumber;
+    use core_foundation::string::{CFString, CFStringRef};
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOPSCopyPowerSourcesInfo() -> CFTypeRef;
+        fn IOPSCopyPowerSourcesList(blob: CFTypeRef) -> CFArrayRef;
+        fn IOPSGetPowerSourceDescription(blob: CFTypeRef, ps: CFTypeRef) -> CFDictionaryRef;
+        fn IOPSGetProvidingPowerSourceType(snapshot: CFTypeRef) -> CFStringRef;
+    }
+
+    // SAFETY: Copy* results are owned (create rule), Get* results are borrowed from
+    // `blob`, which outlives every use below. Null returns are checked before wrapping.
+    unsafe {
+        let blob = IOPSCopyPowerSourcesInfo();
+        if blob.is_null() {
+            return (None, None);
+        }
+        let blob = CFType::wrap_under_create_rule(blob);
+
+        let providing = IOPSGetProvidingPowerSourceType(blob.as_CFTypeRef());
+        let on_ac = if providing.is_null() {
+            None
+        } else {
+            Some(CFString::wrap_under_get_rule(providing).to_string() == "AC Power")
+        };
+
+        let list = IOPSCopyPowerSourcesList(blob.as_CFTypeRef());
+        if list.is_null() {
+            return (None, on_ac);
+        }
+        let list: CFArray<CFType> = CFArray::wrap_under_create_rule(list);
+
+        let mut current = 0.0;
+        let mut max = 0.0;
+        for source in list.iter() {
+            let description =
+                IOPSGetPowerSourceDescription(blob.as_CFTypeRef(), source.as_CFTypeRef());
+            if description.is_null() {
+                continue;
+            }
+            let description: CFDictionary<CFString, CFType> =
+                CFDictionary::wrap_under_get_rule(description);
+            let number = |key: &str| {
+                description
+                    .find(&CFString::new(key))
+                    .and_then(|value| value.downcast::<CFNumber>())
+                    .and_then(|value| value.to_f64())
+            };
+            if let (Some(cap), Some(full)) = (number("Current Capacity"), number("Max Capacity")) {
+                current += cap;
+                max += full;
+            }
+        }
+
+        let battery_pct = if max > 0.0 {
+            Some((current / max * 100.0) as f32)
+        } else {
+            None
+        };
+        (battery_pct, on_ac)
+    }
+}
+
+#[cfg(windows)]
+fn read_battery_status() -> (Option<f32>, Option<bool>) {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    // SAFETY: SYSTEM_POWER_STATUS is plain data and GetSystemPowerStatus only writes into it.
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return (None, None);
+    }
+
+    let on_ac = match status.ACLineStatus {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    };
+    // BatteryFlag 128 means "no system battery"; 255 in either field means unknown.
+    let battery_pct = if status.BatteryFlag & 128 != 0 || status.BatteryLifePercent == 255 {
+        None
+    } else {
+        Some(status.BatteryLifePercent as f32)
+    };
+    (battery_pct, on_ac)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn read_battery_status() -> (Option<f32>, Option<bool>) {
+    (None, None)
+}
+
+// Fallback sensor labels (lowercased) when nothing is labeled "cpu": Intel coretemp,
+// AMD k10temp, ARM SoCs, and the SMC die sensors sysinfo exposes on Apple Silicon.
+const CPU_SENSOR_FALLBACK_LABELS: [&str; 5] = ["package id 0", "tctl", "tdie", "soc", "pmu tdie"];
+
+fn find_cpu_sensor(components: &Components) -> Option<&Component> {
+    let usable = |component: &&Component| {
+        let temp = component.temperature();
+        temp.is_finite() && temp > 0.0
+    };
+    components
+        .iter()
+        .filter(usable)
+        .find(|component| component.label().to_lowercase().contains("cpu"))
+        .or_else(|| {
+            CPU_SENSOR_FALLBACK_LABELS.iter().find_map(|needle| {
+                components
+                    .iter()
+                    .filter(usable)
+                    .find(|component| component.label().to_lowercase().contains(needle))
+            })
+        })
+}
+
+fn read_cpu_temp(components: &Components) -> Option<f32> {
+    find_cpu_sensor(components).map(|component| component.temperature())
+}
+
+// Aggregated over all NVIDIA GPUs: the busiest and hottest device, and the summed VRAM.
+#[cfg_attr(not(feature = "nvml"), allow(dead_code))]
+struct GpuSample {
+    usage_pct: f32,
+    vram_used_bytes: u64,
+    temp_c: Option<f32>,
+}
+
+#[cfg(feature = "nvml")]
+struct GpuMonitor(Option<nvml_wrapper::Nvml>);
+
+#[cfg(feature = "nvml")]
+impl GpuMonitor {
+    fn new() -> Self {
+        match nvml_wrapper::Nvml::init() {
+            Ok(nvml) => Self(Some(nvml)),
+            Err(err) => {
+                info!("NVML unavailable, GPU telemetry disabled: {}", err);
+                Self(None)
+            }
+        }
+    }
+
+    fn sample(&self) -> Option<GpuSample> {
+        use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+
+        let nvml = self.0.as_ref()?;
+        let count = nvml.device_count().ok()?;
+        let mut sample: Option<GpuSample> = None;
+        for index in 0..count {
+            let Ok(device) = nvml.device_by_index(index) else {
+                continue;
+            };
+            let usage_pct = device
+                .utilization_rates()
+                .map(|rates| rates.gpu as f32)
+                .unwrap_or(0.0);
+            let vram_used_bytes = device.memory_info().map(|info| info.used).unwrap_or(0);
+            let temp_c = device
+                .temperature(TemperatureSensor::Gpu)
+                .ok()
+                .map(|temp| temp as f32);
+            let total = sample.get_or_insert(GpuSample {
+                usage_pct: 0.0,
+                vram_used_bytes: 0,
+                temp_c: None,
+            });
+            total.usage_pct = total.usage_pct.max(usage_pct);
+            total.vram_used_bytes += vram_used_bytes;
+            total.temp_c = match (total.temp_c, temp_c) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        sample
+    }
+}
+
+#[cfg(not(feature = "nvml"))]
+struct GpuMonitor;
+
+#[cfg(not(feature = "nvml"))]
+impl GpuMonitor {
+    fn new() -> Self {
+        Self
+    }
+
+    fn sample(&self) -> Option<GpuSample> {
+        None
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+struct ThrottleConfig {
+    cpu_enabled: bool,
+    cpu_pct: f32,
+    cpu_exit_pct: f32,
+    // CPU load only counts once `cpu_window_required` of the last `cpu_window_samples` samples
+    // are over the threshold, so a short burst doesn't throttle.
+    cpu_window_samples: usize,
+    cpu_window_required: usize,
+    temp_enabled: bool,
+    temp_c: f32,
+    temp_exit_c: f32,
+    // Throttle early when the temperature trend would cross `temp_c` within this many seconds;
+    // 0 only looks at the current reading.
+    temp_horizon_secs: u64,
+    battery_enabled: bool,
+    battery_pct: f32,
+    battery_exit_pct: f32,
+    memory_enabled: bool,
+    memory_pct: f32,
+    memory_exit_pct: f32,
+    // Only takes effect with the `nvml` feature and an NVIDIA GPU present.
+    gpu_temp_enabled: bool,
+    gpu_temp_c: f32,
+    gpu_temp_exit_c: f32,
+    // Free space on the data root volume below which `disk_low` is raised.
+    disk_low_bytes: u64,
+    // Sampling interval bounds: the monitor tightens to the minimum under (or near) pressure
+    // and relaxes to the maximum when idle on AC power.
+    min_interval_secs: u64,
+    max_interval_secs: u64,
+    // Minimum time a throttle state must hold before it may flip again.
+    min_dwell_secs: u64,
+    // POST each `throttled` flip to the sidecar so it can shed load without polling the file.
+    notify_enabled: bool,
+    // `http://host:port/path`; defaults to `/throttle` on the sidecar's HTTP API port.
+    notify_url: Option<String>,
+    // Extra fields for the notification body, which always carries `throttled` and `timestamp`.
+    notify_payload: serde_json::Map<String, serde_json::Value>,
+    // Also record each flip in agent_feed.jsonl, next to the agents' own entries.
+    feed_log_enabled: bool,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            cpu_enabled: true,
+            cpu_pct: 70.0,
+            cpu_exit_pct: 60.0,
+            cpu_window_samples: 4,
+            cpu_window_required: 3,
+            temp_enabled: true,
+            temp_c: 85.0,
+            temp_exit_c: 80.0,
+            temp_horizon_secs: 30,
+            battery_enabled: true,
+            battery_pct: 30.0,
+            battery_exit_pct: 35.0,
+            memory_enabled: true,
+            memory_pct: 90.0,
+            memory_exit_pct: 80.0,
+            gpu_temp_enabled: true,
+            gpu_temp_c: 83.0,
+            gpu_temp_exit_c: 78.0,
+            disk_low_bytes: 1024 * 1024 * 1024,
+            min_interval_secs: 1,
+            max_interval_secs: 15,
+            min_dwell_secs: 30,
+            notify_enabled: false,
+            notify_url: None,
+            notify_payload: serde_json::Map::new(),
+            feed_log_enabled: false,
+        }
+    }
+}
+
+struct ResourceSample {
+    cpu_usage: f32,
+    cpu_temp: Option<f32>,
+    // Where the CPU temperature is heading `temp_horizon_secs` from now, if it's rising.
+    predicted_temp: Option<f32>,
+    battery_pct: Option<f32>,
+    on_ac_power: Option<bool>,
+    mem_used_bytes: u64,
+    mem_total_bytes: u64,
+    swap_used_bytes: u64,
+    swap_total_bytes: u64,
+    per_core_usage: Vec<f32>,
+    gpu: Option<GpuSample>,
+}
+
+impl ResourceSample {
+    fn mem_usage_pct(&self) -> f32 {
+        percent(self.mem_used_bytes, self.mem_total_bytes)
+    }
+
+    fn swap_usage_pct(&self) -> f32 {
+        percent(self.swap_used_bytes, self.swap_total_bytes)
+    }
+}
+
+fn percent(used: u64, total: u64) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        (used as f64 / total as f64 * 100.0) as f32
+    }
+}
+
+// How much CPU temperature history the trend is fitted over.
+const TEMP_TREND_WINDOW: Duration = Duration::from_secs(60);
+const TEMP_TREND_MIN_READINGS: usize = 3;
+// A monitor sleep that overran by this much means the machine was suspended.
+const SLEEP_DETECT_GAP: Duration = Duration::from_secs(30);
+
+// A clock that keeps counting while the machine is suspended, which `Instant` doesn't on Linux
+// and macOS. Unlike the wall clock it never jumps when NTP or the user sets the time.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn suspend_clock() -> Option<Duration> {
+    #[cfg(target_os = "linux")]
+    let clock = libc::CLOCK_BOOTTIME;
+    #[cfg(target_os = "macos")]
+    let clock = libc::CLOCK_MONOTONIC;
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: clock_gettime(2) only writes to the timespec passed in.
+    (unsafe { libc::clock_gettime(clock, &mut now) } == 0)
+        .then(|| Duration::new(now.tv_sec as u64, now.tv_nsec as u32))
+}
+
+// On Windows `Instant` already counts suspended time.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn suspend_clock() -> Option<Duration> {
+    None
+}
+
+// How long a sleep started at `mono` / `suspend` really took, including time suspended.
+fn slept_since(mono: Instant, suspend: Option<Duration>) -> Duration {
+    match (suspend, suspend_clock()) {
+        (Some(start), Some(now)) => now.saturating_sub(start).max(mono.elapsed()),
+        _ => mono.elapsed(),
+    }
+}
+
+struct TempTrend {
+    readings: VecDeque<(Instant, f32)>,
+}
+
+impl TempTrend {
+    fn new() -> Self {
+        Self {
+            readings: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, temp: f32) {
+        let now = Instant::now();
+        self.readings.push_back((now, temp));
+        while let Some((at, _)) = self.readings.front() {
+            if now.duration_since(*at) <= TEMP_TREND_WINDOW {
+                break;
+            }
+            self.readings.pop_front();
+        }
+    }
+
+    // Least-squares slope over the window, projected `horizon_secs` past the latest reading.
+    // Only a rising trend produces a prediction.
+    fn predict(&self, horizon_secs: u64) -> Option<f32> {
+        if horizon_secs == 0 || self.readings.len() < TEMP_TREND_MIN_READINGS {
+            return None;
+        }
+        let (start, _) = *self.readings.front()?;
+        let (_, latest) = *self.readings.back()?;
+        let points: Vec<(f64, f64)> = self
+            .readings
+            .iter()
+            .map(|(at, temp)| (at.duration_since(start).as_secs_f64(), *temp as f64))
+            .collect();
+        let n = points.len() as f64;
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_c = points.iter().map(|(_, c)| c).sum::<f64>() / n;
+        let variance: f64 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+        if variance <= f64::EPSILON {
+            return None;
+        }
+        let covariance: f64 = points
+            .iter()
+            .map(|(t, c)| (t - mean_t) * (c - mean_c))
+            .sum();
+        let slope = covariance / variance;
+        if slope <= 0.0 {
+            return None;
+        }
+        Some((latest as f64 + slope * horizon_secs as f64) as f32)
+    }
+}
+
+struct ThrottleLimits {
+    cpu_pct: f32,
+    temp_c: f32,
+    battery_pct: f32,
+    memory_pct: f32,
+    gpu_temp_c: f32,
+}
+
+impl ThrottleConfig {
+    fn entry_limits(&self) -> ThrottleLimits {
+        ThrottleLimits {
+            cpu_pct: self.cpu_pct,
+            temp_c: self.temp_c,
+            battery_pct: self.battery_pct,
+            memory_pct: self.memory_pct,
+            gpu_temp_c: self.gpu_temp_c,
+        }
+    }
+
+    // Exit thresholds sit below the entry ones so load hovering at the limit doesn't flap.
+    fn exit_limits(&self) -> ThrottleLimits {
+        ThrottleLimits {
+            cpu_pct: self.cpu_exit_pct,
+            temp_c: self.temp_exit_c,
+            battery_pct: self.battery_exit_pct,
+            memory_pct: self.memory_exit_pct,
+            gpu_temp_c: self.gpu_temp_exit_c,
+        }
+    }
+
+    // `cpu_usage` is passed separately so the throttle decision can use the sustained load
+    // rather than this sample's.
+    // The conditions over `limits`, named after their `*_enabled` switches.
+    fn exceeded_by(
+        &self,
+        sample: &ResourceSample,
+        cpu_usage: f32,
+        limits: &ThrottleLimits,
+    ) -> Vec<&'static str> {
+        let mut reasons = Vec::new();
+        if self.cpu_enabled && cpu_usage > limits.cpu_pct {
+            reasons.push("cpu");
+        }
+        if self.temp_enabled
+            && sample
+                .cpu_temp
+                .into_iter()
+                .chain(sample.predicted_temp)
+                .any(|temp| temp > limits.temp_c)
+        {
+            reasons.push("temp");
+        }
+        if self.battery_enabled
+            && sample
+                .battery_pct
+                .zip(sample.on_ac_power)
+                .map(|(pct, ac)| pct < limits.battery_pct && !ac)
+                .unwrap_or(false)
+        {
+            reasons.push("battery");
+        }
+        if self.memory_enabled && sample.mem_usage_pct() > limits.memory_pct {
+            reasons.push("memory");
+        }
+        if self.gpu_temp_enabled
+            && sample
+                .gpu
+                .as_ref()
+                .and_then(|gpu| gpu.temp_c)
+                .map(|temp| temp > limits.gpu_temp_c)
+                .unwrap_or(false)
+        {
+            reasons.push("gpu_temp");
+        }
+        reasons
+    }
+
+    fn exceeds(&self, sample: &ResourceSample, cpu_usage: f32, limits: &ThrottleLimits) -> bool {
+        !self.exceeded_by(sample, cpu_usage, limits).is_empty()
+    }
+
+    fn is_exceeded(&self, sample: &ResourceSample) -> bool {
+        self.exceeds(sample, sample.cpu_usage, &self.entry_limits())
+    }
+
+    fn is_near(&self, sample: &ResourceSample) -> bool {
+        let limits = ThrottleLimits {
+            cpu_pct: self.cpu_pct * 0.9,
+            temp_c: self.temp_c - 5.0,
+            battery_pct: self.battery_pct + 5.0,
+            memory_pct: self.memory_pct * 0.9,
+            gpu_temp_c: self.gpu_temp_c - 5.0,
+        };
+        self.exceeds(sample, sample.cpu_usage, &limits)
+    }
+
+    fn next_interval(&self, sample: &ResourceSample, throttled: bool) -> Duration {
+        let min = self.min_interval_secs.max(1);
+        let max = self.max_interval_secs.max(min);
+        let secs = if throttled || self.is_near(sample) {
+            min
+        } else if sample.on_ac_power != Some(false) {
+            max
+        } else {
+            DEFAULT_RESOURCE_INTERVAL_SECS.clamp(min, max)
+        };
+        Duration::from_secs(secs)
+    }
+}
+
+struct ThrottleState {
+    throttled: bool,
+    changed_at: Option<Instant>,
+    // CPU usage of the most recent samples, newest last.
+    cpu_window: VecDeque<f32>,
+    // What's holding the throttle on; empty while unthrottled.
+    reasons: Vec<&'static str>,
+}
+
+impl ThrottleState {
+    fn new() -> Self {
+        Self {
+            throttled: false,
+            changed_at: None,
+            cpu_window: VecDeque::new(),
+            reasons: Vec::new(),
+        }
+    }
+
+    // The `required`-th highest reading in the window: it's over a threshold exactly when at
+    // least `required` samples are. Zero until the window holds that many.
+    fn sustained_cpu(&self, config: &ThrottleConfig) -> f32 {
+        let required = config
+            .cpu_window_required
+            .clamp(1, config.cpu_window_samples.max(1));
+        let mut readings: Vec<f32> = self.cpu_window.iter().copied().collect();
+        readings.sort_by(|a, b| b.total_cmp(a));
+        readings.get(required - 1).copied().unwrap_or(0.0)
+    }
+
+    fn update(&mut self, config: &ThrottleConfig, sample: &ResourceSample) -> bool {
+        self.cpu_window.push_back(sample.cpu_usage);
+        while self.cpu_window.len() > config.cpu_window_samples.max(1) {
+            self.cpu_window.pop_front();
+        }
+        let cpu_usage = self.sustained_cpu(config);
+        let limits = if self.throttled {
+            config.exit_limits()
+        } else {
+            config.entry_limits()
+        };
+        let exceeded = config.exceeded_by(sample, cpu_usage, &limits);
+        let wanted = !exceeded.is_empty();
+        let dwell_elapsed = self
+            .changed_at
+            .map(|at| at.elapsed() >= Duration::from_secs(config.min_dwell_secs))
+            .unwrap_or(true);
+        if wanted != self.throttled && dwell_elapsed {
+            self.throttled = wanted;
+            self.changed_at = Some(Instant::now());
+        }
+        // Held on by the dwell time alone, the last known reasons still explain it.
+        if !self.throttled {
+            self.reasons.clear();
+        } else if wanted {
+            self.reasons = exceeded;
+        }
+        self.throttled
+    }
+}
+
+// Changes smaller than these don't warrant a new resource_state; RESOURCE_HEARTBEAT bounds how
+// long the UI can go without one.
+const RESOURCE_CPU_DELTA_PCT: f32 = 5.0;
+const RESOURCE_TEMP_DELTA_C: f32 = 2.0;
+const RESOURCE_BATTERY_DELTA_PCT: f32 = 2.0;
+const RESOURCE_MEMORY_DELTA_PCT: f32 = 5.0;
+const RESOURCE_HEARTBEAT: Duration = Duration::from_secs(60);
+// Pause before a crashed resource monitor is started again.
+const RESOURCE_MONITOR_RESTART_DELAY: Duration = Duration::from_secs(5);
+
+// One metric's refresh or read. A panic inside sysinfo (or a platform reader) is logged and the
+// metric comes back as None instead of ending the monitor.
+fn read_metric<T>(metric: &str, read: impl FnOnce() -> T) -> Option<T> {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(read)) {
+        Ok(value) => Some(value),
+        Err(_) => {
+            warn!("Reading {} failed; reporting it as unavailable", metric);
+            None
+        }
+    }
+}
+
+struct EmittedResources {
+    cpu_usage: f32,
+    cpu_temp: Option<f32>,
+    battery_pct: Option<f32>,
+    on_ac_power: Option<bool>,
+    mem_usage_pct: f32,
+    exceeded: bool,
+    throttled: bool,
+    disk_low: bool,
+    at: Instant,
+}
+
+fn moved_by(previous: Option<f32>, current: Option<f32>, delta: f32) -> bool {
+    match (previous, current) {
+        (Some(previous), Some(current)) => (current - previous).abs() >= delta,
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+struct ResourceEmitGate {
+    last: Option<EmittedResources>,
+}
+
+impl ResourceEmitGate {
+    fn new() -> Self {
+        Self { last: None }
+    }
+
+    fn should_emit(
+        &mut self,
+        config: &ThrottleConfig,
+        sample: &ResourceSample,
+        throttled: bool,
+        disk_low: bool,
+    ) -> bool {
+        let current = EmittedResources {
+            cpu_usage: sample.cpu_usage,
+            cpu_temp: sample.cpu_temp,
+            battery_pct: sample.battery_pct,
+            on_ac_power: sample.on_ac_power,
+            mem_usage_pct: sample.mem_usage_pct(),
+            exceeded: config.is_exceeded(sample),
+            throttled,
+            disk_low,
+            at: Instant::now(),
+        };
+        let changed = match &self.last {
+            None => true,
+            Some(last) => {
+                last.at.elapsed() >= RESOURCE_HEARTBEAT
+                    || last.throttled != current.throttled
+                    || last.exceeded != current.exceeded
+                    || last.disk_low != current.disk_low
+                    || last.on_ac_power != current.on_ac_power
+                    || (current.cpu_usage - last.cpu_usage).abs() >= RESOURCE_CPU_DELTA_PCT
+                    || (current.mem_usage_pct - last.mem_usage_pct).abs()
+                        >= RESOURCE_MEMORY_DELTA_PCT
+                    || moved_by(last.cpu_temp, current.cpu_temp, RESOURCE_TEMP_DELTA_C)
+                    || moved_by(
+                        last.battery_pct,
+                        current.battery_pct,
+                        RESOURCE_BATTERY_DELTA_PCT,
+                    )
+            }
+        };
+        if changed {
+            self.last = Some(current);
+        }
+        changed
+    }
+}
+
+fn throttle_config_path(data_root: &Path) -> PathBuf {
+    data_root.join("mesh").join("throttle_config.json")
+}
+
+// Objects are merged key by key; anything else in `overlay` replaces the base value.
+fn merge_json(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+// The file is usually hand-edited and may set only a few fields; those are merged over the
+// defaults. Unknown keys are ignored and returned so the caller can warn about them. A file that
+// doesn't parse, or a field of the wrong type, is an error naming the problem rather than a
+// silent fall back to the defaults.
+fn load_throttle_config(data_root: &Path) -> Result<(ThrottleConfig, Vec<String>), AppError> {
+    let path = throttle_config_path(data_root);
+    let Some(contents) = read_optional_file(path.clone())? else {
+        return Ok((ThrottleConfig::default(), Vec::new()));
+    };
+    let invalid =
+        |detail: String| AppError::InvalidInput(format!("{}: {}", path.display(), detail));
+    let overlay: serde_json::Value =
+        serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+    let serde_json::Value::Object(fields) = overlay else {
+        return Err(invalid("expected a JSON object".to_string()));
+    };
+
+    let defaults = serde_json::to_value(ThrottleConfig::default())
+        .map_err(|e| invalid(format!("failed to serialize defaults: {e}")))?;
+    let mut merged = defaults.clone();
+    let mut unknown = Vec::new();
+    for (key, value) in fields {
+        if defaults.get(&key).is_none() {
+            unknown.push(key);
+            continue;
+        }
+        // Checked one field at a time so the error can say which one is wrong.
+        let mut single = defaults.clone();
+        merge_json(
+            &mut single,
+            serde_json::json!({ key.as_str(): value.clone() }),
+        );
+        if let Err(err) = serde_json::from_value::<ThrottleConfig>(single) {
+            return Err(invalid(format!("field {:?}: {}", key, err)));
+        }
+        merge_json(&mut merged, serde_json::json!({ key: value }));
+    }
+    let config = serde_json::from_value(merged).map_err(|e| invalid(e.to_string()))?;
+    Ok((config, unknown))
+}
+
+fn warn_unknown_throttle_fields(data_root: &Path, unknown: &[String]) {
+    if !unknown.is_empty() {
+        warn!(
+            "{}: ignoring unknown fields {}",
+            throttle_config_path(data_root).display(),
+            unknown.join(", ")
+        );
+    }
+}
+
+#[tauri::command]
+fn get_throttle_config(app: tauri::AppHandle) -> Result<ThrottleConfig, AppError> {
+    let root = data_root(&app);
+    let (config, unknown) = load_throttle_config(&root)?;
+    warn_unknown_throttle_fields(&root, &unknown);
+    Ok(config)
+}
+
+#[tauri::command]
+fn set_throttle_config(app: tauri::AppHandle, config: ThrottleConfig) -> Result<(), AppError> {
+    ensure_writable(&app)?;
+    let payload = serde_json::to_string_pretty(&config)
+        .map_err(|e| AppError::InvalidInput(format!("Failed to serialize throttle config: {e}")))?;
+    write_json_atomic(&throttle_config_path(&data_root(&app)), &payload)
+}
+
+// (free, total) bytes of the volume holding `path`, picking the most specific mount point.
+fn disk_space_for(disks: &Disks, path: &Path) -> Option<(u64, u64)> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    disks
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| (disk.available_space(), disk.total_space()))
+}
+
+// Best effort: an unreachable or failing target is logged and the monitor carries on.
+async fn notify_throttle_change(url: String, body: serde_json::Value) {
+    let (host, port, path) = match parse_address(&url, "http://") {
+        Ok(target) => target,
+        Err(err) => {
+            warn!("Throttle notification skipped: {}", err);
+            return;
+        }
+    };
+    let body = body.to_string();
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}:{port}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let sent = tokio::time::timeout(
+        HEALTH_CHECK_TIMEOUT,
+        request_status_line(&host, port, &request),
+    )
+    .await;
+    match sent {
+        Ok(Ok(status)) => {
+            let accepted = status
+                .split_whitespace()
+                .nth(1)
+                .is_some_and(|code| code.starts_with('2'));
+            if !accepted {
+                warn!("Throttle notification to {} failed: {}", url, status);
+            }
+        }
+        Ok(Err(probe)) => warn!("Throttle notification to {} failed: {:?}", url, probe),
+        Err(_) => warn!("Throttle notification to {} timed out", url),
+    }
+}
+
+async fn start_resource_monitor(app: tauri::AppHandle, state: Arc<Mutex<SidecarState>>) {
+    let guard = state.lock().await;
+    let Some(running) = TaskFlagGuard::claim(&guard.resource_task_running) else {
+        return;
+    };
+    let data_root = data_root(&app);
+    let needs_previous = guard.last_resource.is_none();
+    drop(guard);
+    // Serve the previous run's snapshot, flagged stale, until the first sample replaces it.
+    if needs_previous {
+        let task_app = app.clone();
+        let previous = off_runtime(move || read_resource_state_file(&task_app).ok().flatten())
+            .await
+            .ok()
+            .flatten();
+        let mut guard = state.lock().await;
+        if guard.last_resource.is_none() && previous.is_some() {
+            guard.last_resource = previous;
+            guard.resource_stale = true;
+        }
+    }
+
+    // The monitor itself runs in its own task so a panic (seen from sysinfo on some platforms)
+    // only takes down that run; this one notices and starts a fresh one.
+    let task = async move {
+        let _running = running;
+        loop {
+            let run = run_resource_monitor(app.clone(), state.clone(), data_root.clone());
+            let handle = tauri::async_runtime::spawn(
+                run.instrument(tracing::info_span!("resource_monitor")),
+            );
+            match handle.await {
+                Ok(()) => warn!("Resource monitor stopped unexpectedly; restarting"),
+                Err(err) => error!("Resource monitor crashed ({}); restarting", err),
+            }
+            tokio::time::sleep(RESOURCE_MONITOR_RESTART_DELAY).await;
+        }
+    };
+    tauri::async_runtime::spawn(task);
+}
+
+async fn run_resource_monitor(
+    app: tauri::AppHandle,
+    state: Arc<Mutex<SidecarState>>,
+    data_root: PathBuf,
+) {
+    let mut system = System::new_all();
+    // CPU usage is a delta between two refreshes, and on some platforms the one done by
+    // new_all doesn't count as a baseline. Take an explicit one and let it age; the first
+    // loop sample is still discarded (see below) so a bogus ~0% can't clear a throttle.
+    system.refresh_cpu();
+    tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+    let mut components = Components::new_with_refreshed_list();
+    let mut disks = Disks::new_with_refreshed_list();
+    let mut throttle = ThrottleState::new();
+    let gpu = GpuMonitor::new();
+    let mut temp_trend = TempTrend::new();
+    let mut networks = Networks::new_with_refreshed_list();
+    let mut networks_refreshed_at = Instant::now();
+    let mut emit_gate = ResourceEmitGate::new();
+    let mut was_disk_low = false;
+    let mut was_battery_low = false;
+    // The last known power source; an unknown reading doesn't count as a change.
+    let mut last_on_ac_power: Option<bool> = None;
+    // Successful CPU refreshes since launch or the last resume. The first one's usage spans
+    // whatever came before it, so a sample needs at least two.
+    let mut cpu_warm_refreshes = 0u32;
+    let mut last_throttled = false;
+    // The last config that loaded; a broken edit keeps it in force until it's fixed.
+    let mut config = ThrottleConfig::default();
+    let mut config_error: Option<String> = None;
+    let mut unknown_fields: Vec<String> = Vec::new();
+    let readonly = app.state::<ReadOnly>().0;
+    let mut history = ResourceHistory::load(
+        data_root.join("mesh").join("resource_history.jsonl"),
+        readonly,
+    );
+    // When the last sleep started on both clocks, and how long it was meant to take.
+    let mut last_sleep: Option<(Instant, Option<Duration>, Duration)> = None;
+    match find_cpu_sensor(&components) {
+        Some(sensor) => info!("CPU temperature sensor: {}", sensor.label()),
+        None => warn!("No CPU temperature sensor found; temperature throttling inactive"),
+    }
+    loop {
+        if let Some((mono, suspend, planned)) = last_sleep.take() {
+            let slept = slept_since(mono, suspend);
+            if slept > planned + SLEEP_DETECT_GAP {
+                info!("System resumed after about {}s", slept.as_secs());
+                let _ = app.emit_all(
+                    "system_resumed",
+                    serde_json::json!({ "slept_secs": slept.as_secs() }),
+                );
+                // Counters and the temperature trend straddle the gap, so start over as
+                // after launch rather than report a spike.
+                temp_trend = TempTrend::new();
+                cpu_warm_refreshes = 0;
+            }
+        }
+        if state.lock().await.resource_paused {
+            let pause = Duration::from_secs(1);
+            last_sleep = Some((Instant::now(), suspend_clock(), pause));
+            tokio::time::sleep(pause).await;
+            continue;
+        }
+
+        let cpu_refreshed = read_metric("CPU usage", || system.refresh_cpu()).is_some();
+        if cpu_refreshed {
+            cpu_warm_refreshes += 1;
+        }
+        let memory_refreshed = read_metric("memory", || system.refresh_memory()).is_some();
+        let components_refreshed = read_metric("sensors", || components.refresh()).is_some();
+        let disks_refreshed = read_metric("disks", || disks.refresh()).is_some();
+        // `received`/`transmitted` count bytes since the previous refresh, so dividing by the
+        // time since then gives the average rate over the sleep (and over any pause).
+        let networks_refreshed = read_metric("network", || networks.refresh()).is_some();
+        let net_elapsed = networks_refreshed_at.elapsed().as_secs_f64().max(0.001);
+        networks_refreshed_at = Instant::now();
+        let net_rates = networks_refreshed.then(|| {
+            let (rx, tx) = networks
+                .iter()
+                .filter(|(name, _)| name.as_str() != "lo")
+                .fold((0u64, 0u64), |(rx, tx), (_, data)| {
+                    (rx + data.received(), tx + data.transmitted())
+                });
+            (
+                (rx as f64 / net_elapsed) as u64,
+                (tx as f64 / net_elapsed) as u64,
+            )
+        });
+        // Discard the baseline and refresh again shortly. A failed refresh doesn't count and
+        // is retried below at the normal pace.
+        if cpu_refreshed && cpu_warm_refreshes < 2 {
+            tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+            continue;
+        }
+
+        // Re-read every cycle so set_throttle_config (or a hand edit) applies immediately.
+        let root = data_root.clone();
+        let loaded = off_runtime(move || load_throttle_config(&root))
+            .await
+            .and_then(|loaded| loaded);
+        match loaded {
+            Ok((loaded, unknown)) => {
+                // Warn once per edit, not on every sample.
+                if unknown != unknown_fields {
+                    warn_unknown_throttle_fields(&data_root, &unknown);
+                    unknown_fields = unknown;
+                }
+                config = loaded;
+                config_error = None;
+            }
+            Err(err) => {
+                let message = err.to_string();
+                if config_error.as_ref() != Some(&message) {
+                    error!("{}; keeping the previous throttle config", message);
+                    config_error = Some(message);
+                }
+            }
+        }
+
+        // Throttling and the emit gate are built on CPU and memory, so without them there's no
+        // sample this round; everything else can be missing on its own.
+        if !cpu_refreshed || !memory_refreshed {
+            let retry = Duration::from_secs(config.min_interval_secs.max(1));
+            last_sleep = Some((Instant::now(), suspend_clock(), retry));
+            tokio::time::sleep(retry).await;
+            continue;
+        }
+        let (battery_pct, on_ac_power) =
+            read_metric("battery", read_battery_status).unwrap_or((None, None));
+        let cpu_temp = if components_refreshed {
+            read_metric("CPU temperature", || read_cpu_temp(&components)).flatten()
+        } else {
+            None
+        };
+        if let Some(temp) = cpu_temp {
+            temp_trend.push(temp);
+        }
+        let sample = ResourceSample {
+            cpu_usage: system.global_cpu_info().cpu_usage(),
+            cpu_temp,
+            predicted_temp: temp_trend.predict(config.temp_horizon_secs),
+            battery_pct,
+            on_ac_power,
+            mem_used_bytes: system.used_memory(),
+            mem_total_bytes: system.total_memory(),
+            swap_used_bytes: system.used_swap(),
+            swap_total_bytes: system.total_swap(),
+            per_core_usage: system.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+            gpu: read_metric("GPU", || gpu.sample()).flatten(),
+        };
+
+        // What the sidecar itself uses, as a share of the whole machine like cpu_usage_pct.
+        // None while it isn't ours to measure (stopped, external) or after it died.
+        let sidecar_pid = state
+            .lock()
+            .await
+            .process
+            .as_ref()
+            .map(|child| Pid::from_u32(child.pid()));
+        let sidecar_usage = sidecar_pid.and_then(|pid| {
+            read_metric("sidecar process usage", || {
+                if !system.refresh_process(pid) {
+                    return None;
+                }
+                let process = system.process(pid)?;
+                let cpus = system.cpus().len().max(1) as f32;
+                Some((process.cpu_usage() / cpus, process.memory()))
+            })
+            .flatten()
+        });
+
+        let throttled = throttle.update(&config, &sample);
+        if throttled != last_throttled && config.notify_enabled && !readonly {
+            let target = match &config.notify_url {
+                Some(url) => Some(url.clone()),
+                // Nobody to tell while the sidecar is down.
+                None => {
+                    let guard = state.lock().await;
+                    (guard.mode() != SidecarMode::Stopped)
+                        .then(|| format!("http://127.0.0.1:{}/throttle", guard.metrics_port))
+                }
+            };
+            if let Some(url) = target {
+                let mut body = config.notify_payload.clone();
+                body.insert("throttled".to_string(), throttled.into());
+                body.insert(
+                    "timestamp".to_string(),
+                    chrono::Utc::now().timestamp().into(),
+                );
+                tauri::async_runtime::spawn(notify_throttle_change(url, body.into()));
+            }
+        }
+        if throttled != last_throttled && config.feed_log_enabled && !readonly {
+            let entry = serde_json::json!({
+                "type": "throttle",
+                "throttled": throttled,
+                "reasons": &throttle.reasons,
+                "timestamp": chrono::Utc::now().timestamp_millis() as f64 / 1000.0,
+            });
+            if let Err(err) = append_feed_line(&app, &entry) {
+                warn!("Failed to record throttle change in the feed: {}", err);
+            }
+        }
+        last_throttled = throttled;
+
+        let disk_space = if disks_refreshed {
+            read_metric("disk space", || disk_space_for(&disks, &data_root)).flatten()
+        } else {
+            None
+        };
+        let disk_low = disk_space
+            .map(|(free, _)| free < config.disk_low_bytes)
+            .unwrap_or(false);
+        if disk_low && !was_disk_low {
+            let _ = app.emit_all(
+                "disk_low",
+                serde_json::json!({
+                    "path": data_root.to_string_lossy(),
+                    "disk_free_bytes": disk_space.map(|(free, _)| free),
+                    "threshold_bytes": config.disk_low_bytes,
+                }),
+            );
+        }
+        was_disk_low = disk_low;
+
+        let battery_low = sample
+            .battery_pct
+            .map(|pct| pct < config.battery_pct)
+            .unwrap_or(false);
+        if battery_low && !was_battery_low {
+            let _ = app.emit_all(
+                "battery_low",
+                serde_json::json!({
+                    "battery_pct": sample.battery_pct,
+                    "threshold_pct": config.battery_pct,
+                    "on_ac_power": sample.on_ac_power,
+                }),
+            );
+        }
+        was_battery_low = battery_low;
+        if let Some(on_ac_power) = sample.on_ac_power {
+            if last_on_ac_power.is_some_and(|last| last != on_ac_power) {
+                let _ = app.emit_all(
+                    "power_source_changed",
+                    serde_json::json!({
+                        "on_ac_power": on_ac_power,
+                        "battery_pct": sample.battery_pct,
+                    }),
+                );
+            }
+            last_on_ac_power = Some(on_ac_power);
+        }
+
+        let payload = serde_json::json!({
+            "timestamp": chrono::Utc::now().timestamp(),
+            "cpu_usage_pct": sample.cpu_usage,
+            "cpu_temp_c": sample.cpu_temp,
+            "predicted_temp_c": sample.predicted_temp,
+            "battery_pct": sample.battery_pct,
+            "on_ac_power": sample.on_ac_power,
+            "mem_used_bytes": sample.mem_used_bytes,
+            "mem_total_bytes": sample.mem_total_bytes,
+            "mem_usage_pct": sample.mem_usage_pct(),
+            "swap_usage_pct": sample.swap_usage_pct(),
+            "per_core_usage": &sample.per_core_usage,
+            "sidecar_cpu_pct": sidecar_usage.map(|(cpu, _)| cpu),
+            "sidecar_mem_bytes": sidecar_usage.map(|(_, mem)| mem),
+            "net_rx_bytes_per_sec": net_rates.map(|(rx, _)| rx),
+            "net_tx_bytes_per_sec": net_rates.map(|(_, tx)| tx),
+            "gpu_usage_pct": sample.gpu.as_ref().map(|gpu| gpu.usage_pct),
+            "vram_used_bytes": sample.gpu.as_ref().map(|gpu| gpu.vram_used_bytes),
+            "gpu_temp_c": sample.gpu.as_ref().and_then(|gpu| gpu.temp_c),
+            "disk_free_bytes": disk_space.map(|(free, _)| free),
+            "disk_total_bytes": disk_space.map(|(_, total)| total),
+            "disk_low": disk_low,
+            "throttled": throttled,
+            "throttle_reasons": &throttle.reasons,
+        });
+
+        // Observers sample and emit but leave the files to whoever runs the node.
+        if !readonly {
+            if let Err(err) = history.push(payload.to_string()) {
+                warn!("{}", err);
+            }
+        }
+        // History keeps every sample; the state file and event only follow real changes.
+        if emit_gate.should_emit(&config, &sample, throttled, disk_low) {
+            if !readonly {
+                let target = data_root.join("mesh").join("resource_state.json");
+                let _ = write_json_atomic(&target, &payload.to_string());
+            }
+            let mut guard = state.lock().await;
+            guard.last_resource = Some(payload.clone());
+            guard.resource_stale = false;
+            drop(guard);
+            let _ = app.emit_all("resource_state", payload);
+        }
+        if !readonly {
+            compact_feed_if_oversized(&app).await;
+        }
+
+        let interval = config.next_interval(&sample, throttled);
+        state.lock().await.resource_interval_secs = interval.as_secs();
+        last_sleep = Some((Instant::now(), suspend_clock(), interval));
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[tauri::command]
+async fn pause_resource_monitor(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+) -> Result<bool, AppError> {
+    state.lock().await.resource_paused = true;
+    Ok(true)
+}
+
+#[tauri::command]
+async fn resume_resource_monitor(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+) -> Result<bool, AppError> {
+    state.lock().await.resource_paused = false;
+    Ok(false)
+}
+
+#[tauri::command]
+async fn resource_monitor_paused(
+    state: State<'_, Arc<Mutex<SidecarState>>>,
+) -> Result<bool, AppError> {
+    Ok(state.lock().await.resource_paused)
+}
+
+// Optional Prometheus scrape target for fleet monitoring; nothing listens until
+// start_metrics_endpoint is called.
+struct MetricsEndpoint(Mutex<Option<(u16, tauri::async_runtime::JoinHandle<()>)>>);
+
+const METRICS_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// (metric, resource_state key, help)
+const PROMETHEUS_RESOURCE_GAUGES: [(&str, &str, &str); 9] = [
+    (
+        "project_dawn_cpu_usage_percent",
+        "cpu_usage_pct",
+        "Global CPU usage.",
+    ),
+    (
+        "project_dawn_cpu_temp_celsius",
+        "cpu_temp_c",
+        "CPU package temperature.",
+    ),
+    (
+        "project_dawn_battery_percent",
+        "battery_pct",
+        "Battery charge.",
+    ),
+    (
+        "project_dawn_memory_usage_percent",
+        "mem_usage_pct",
+        "Physical memory in use.",
+    ),
+    (
+        "project_dawn_sidecar_cpu_usage_percent",
+        "sidecar_cpu_pct",
+        "CPU used by the default sidecar process, as a share of the whole machine.",
+    ),
+    (
+        "project_dawn_sidecar_memory_bytes",
+        "sidecar_mem_bytes",
+        "Resident memory of the default sidecar process.",
+    ),
+    (
+        "project_dawn_disk_free_bytes",
+        "disk_free_bytes",
+        "Free space on the data root volume.",
+    ),
+    (
+        "project_dawn_gpu_usage_percent",
+        "gpu_usage_pct",
+        "GPU utilization.",
+    ),
+    (
+        "project_dawn_gpu_temp_celsius",
+        "gpu_temp_c",
+        "GPU temperature.",
+    ),
+];
+
+async fn render_prometheus_metrics(app: &tauri::AppHandle) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    // A snapshot left over from the previous run isn't a reading; export nothing until a fresh one.
+    let resource = {
+        let state = app.state::<Arc<Mutex<SidecarState>>>();
+        let guard = state.lock().await;
+        if guard.resource_stale {
+            serde_json::Value::default()
+        } else {
+            guard.last_resource.clone().unwrap_or_default()
+        }
+    };
+    for (metric, key, help) in PROMETHEUS_RESOURCE_GAUGES {
+        // Readings the machine doesn't have (no sensor, no battery) are left out, not zeroed.
+        let Some(value) = resource.get(key).and_then(serde_json::Value::as_f64) else {
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            "# HELP {metric} {help}\n# TYPE {metric} gauge\n{metric} {value}"
+        );
+    }
+    if let Some(throttled) = resource
+        .get("throttled")
+        .and_then(serde_json::Value::as_bool)
+    {
+        let _ = writeln!(
+            out,
+            "# HELP project_dawn_throttled Whether the resource monitor is throttling.\n\
+             # TYPE project_dawn_throttled gauge\nproject_dawn_throttled {}",
+            u8::from(throttled)
+        );
+    }
+
+    let mut sidecars = Vec::new();
+    for state in app.state::<SidecarRegistry>().all() {
+        let guard = state.lock().await;
+        sidecars.push((guard.name.clone(), guard.mode(), guard.health.clone()));
+    }
+    sidecars.sort_by(|a, b| a.0.cmp(&b.0));
+    let families: [(&str, &str, &str, fn(SidecarMode, &HealthStatus) -> u64); 3] = [
+        (
+            "project_dawn_sidecar_up",
+            "gauge",
+            "Whether the sidecar process is running (or attached).",
+            |mode, _| u64::from(mode != SidecarMode::Stopped),
+        ),
+        (
+            "project_dawn_sidecar_health_consecutive_failures",
+            "gauge",
+            "Failed health checks since the last success.",
+            |_, health| u64::from(health.consecutive_failures),
+        ),
+        (
+            "project_dawn_sidecar_health_failures_total",
+            "counter",
+            "Failed health checks since the sidecar was started or attached.",
+            |_, health| health.total_failures,
+        ),
+    ];
+    for (metric, kind, help, value) in families {
+        let _ = writeln!(out, "# HELP {metric} {help}\n# TYPE {metric} {kind}");
+        for (name, mode, health) in &sidecars {
+            let _ = writeln!(out, "{metric}{{name=\"{name}\"}} {}", value(*mode, health));
+        }
+    }
+    out
+}
+
+async fn serve_metrics_connection(app: tauri::AppHandle, mut stream: tokio::net::TcpStream) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 8192 {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => request.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = request_line.next();
+    let path = request_line
+        .next()
+        .map(|target| target.split('?').next().unwrap_or_default());
+    let response = if method == Some("GET") && path == Some("/metrics") {
+        let body = render_prometheus_metrics(&app).await;
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+// Binds 127.0.0.1 unless `bind_all` is set. Port 0 picks a free port; the bound port is
+// returned either way.
+#[tauri::command]
+async fn start_metrics_endpoint(
+    app: tauri::AppHandle,
+    endpoint: State<'_, MetricsEndpoint>,
+    port: u16,
+    bind_all: Option<bool>,
+) -> Result<u16, AppError> {
+    let mut guard = endpoint.0.lock().await;
+    if let Some((running_port, _)) = guard.as_ref() {
+        if *running_port == port || port == 0 {
+            return Ok(*running_port);
+        }
+        return Err(AppError::InvalidInput(format!(
+            "Metrics endpoint already serving on port {}; stop it first",
+            running_port
+        )));
+    }
+    let host = if bind_all.unwrap_or(false) {
+        "0.0.0.0"
+    } else {
+        "127.0.0.1"
+    };
+    let listener = tokio::net::TcpListener::bind((host, port))
+        .await
+        .map_err(|e| {
+            AppError::io(
+                format!("Failed to bind metrics endpoint on {host}:{port}"),
+                e,
+            )
+        })?;
+    let bound_port = listener
+        .local_addr()
+        .map_err(|e| AppError::io("Failed to read metrics endpoint address", e))?
+        .port();
+    let task = tauri::async_runtime::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    warn!("Metrics endpoint accept failed: {}", err);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = tokio::time::timeout(
+                    METRICS_REQUEST_TIMEOUT,
+                    serve_metrics_connection(app, stream),
+                )
+                .await;
+            });
+        }
+    });
+    *guard = Some((bound_port, task));
+    info!(
+        "Serving Prometheus metrics on http://{}:{}/metrics",
+        host, bound_port
+    );
+    Ok(bound_port)
+}
+
+#[tauri::command]
+async fn stop_metrics_endpoint(endpoint: State<'_, MetricsEndpoint>) -> Result<bool, AppError> {
+    match endpoint.0.lock().await.take() {
+        Some((_, task)) => {
+            task.abort();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+// Console output plus JSON lines in `<data root>/logs/app.log`; `RUST_LOG` sets the levels
+// (info by default).
+// `file_log` is off in read-only mode, which leaves the data root untouched; the console still
+// gets everything.
+fn init_tracing(root: &Path, file_log: bool) {
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::{fmt as log_fmt, EnvFilter};
+
+    let log_dir = root.join("logs");
+    let log_file = if file_log {
+        fs::create_dir_all(&log_dir)
+            .and_then(|()| {
+                fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(log_dir.join("app.log"))
+            })
+            .map(Some)
+    } else {
+        Ok(None)
+    };
+    let (file_layer, file_error) = match log_file {
+        Ok(Some(file)) => (
+            Some(
+                log_fmt::layer()
+                    .json()
+                    .with_writer(std::sync::Mutex::new(file)),
+            ),
+            None,
+        ),
+        Ok(None) => (None, None),
+        Err(err) => (None, Some(err)),
+    };
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with(log_fmt::layer())
+        .with(file_layer)
+        .init();
+    if let Some(err) = file_error {
+        warn!(
+            "Failed to open {}: {}",
+            log_dir.join("app.log").display(),
+            err
+        );
+    }
+}
+
+fn main() {
+    tauri::Builder::default()
+        .setup(|app| {
+            let sidecar_state = Arc::new(Mutex::new(SidecarState::new(DEFAULT_SIDECAR_NAME)));
+            app.manage(sidecar_state.clone());
+            app.manage(SidecarRegistry::new(sidecar_state.clone()));
+
+            let app_handle = app.handle();
+            let root = data_root(&app_handle);
+            let readonly = readonly_from_env();
+            app.manage(ReadOnly(readonly));
+            init_tracing(&root, !readonly);
+            if readonly {
+                info!("Read-only mode: nothing will be spawned or written");
+            }
+            // An observer only needs the root to exist; creating or probing it would write.
+            let root_check = if readonly {
+                if root.is_dir() {
+                    Ok(())
+                } else {
+                    Err(AppError::NotFound(format!(
+                        "Data root {} does not exist",
+                        root.display()
+                    )))
+                }
+            } else {
+                ensure_data_root(&root)
+            };
+            if let Err(err) = &root_check {
+                error!("{}", err);
+                let _ = app_handle.emit_all(
+                    "data_root_error",
+                    serde_json::json!({ "path": root.to_string_lossy(), "error": err }),
+                );
+            }
+            if root_check.is_ok() && !readonly {
+                match recover_write_journal(&write_journal_path(&root)) {
+                    Ok(0) => {}
+                    Ok(replayed) => info!(
+                        "Finished {} interrupted write(s) from the journal",
+                        replayed
+                    ),
+                    Err(err) => warn!("{}", err),
+                }
+            }
+            app.manage(DataRootCheck(root_check.err()));
+
+            let log_path = data_root(&app_handle).join("logs").join("sidecar.log");
+            app.manage(SidecarLog(std::sync::Mutex::new(RotatingLog::new(
+                log_path,
+            ))));
+            app.manage(SidecarLogStreaming(AtomicBool::new(false)));
+            app.manage(Maintenance(std::sync::Mutex::new(
+                MaintenanceState::default(),
+            )));
+            app.manage(LogFollowers(std::sync::Mutex::new(HashMap::new())));
+            app.manage(MetricsEndpoint(Mutex::new(None)));
+            app.manage(start_sidecar_socket(app_handle.clone()));
+            app.manage(FeedWatcher(std::sync::Mutex::new(None)));
+            app.manage(FeedWriter(std::sync::Mutex::new(FeedRewrites::default())));
+            let manifest_snapshot = ManifestSnapshot::load(&manifest_path(&app_handle));
+            app.manage(manifest_snapshot.clone());
+            match start_data_watcher(&app_handle, manifest_snapshot) {
+                Ok(watcher) => {
+                    app.manage(DataWatcher(std::sync::Mutex::new(watcher)));
+                }
+                Err(err) => error!("{}", err),
+            }
+
+            tauri::async_runtime::spawn(start_resource_monitor(
+                app_handle.clone(),
+                sidecar_state.clone(),
+            ));
+            if !readonly && load_app_settings(&app_handle).autostart {
+                tauri::async_runtime::spawn(autostart_sidecar(app_handle, sidecar_state.clone()));
+            }
+            
+            // Cleanup on app exit
+            let close_handle = app.handle();
+            app.listen_global("tauri://close-requested", move |_event| {
+                for state in close_handle.state::<SidecarRegistry>().all() {
+                    tauri::async_runtime::spawn(async move {
+                        let name = state.lock().await.name.clone();
+                        info!("Stopping sidecar process {}...", name);
+                        let timeout = Duration::from_millis(DEFAULT_SHUTDOWN_TIMEOUT_MS);
+                        match shutdown_sidecar(&state, timeout).await {
+                            StopOutcome::NotRunning => {}
+                            StopOutcome::Clean => info!("Sidecar {} stopped", name),
+                            StopOutcome::Forced => {
+                                info!("Sidecar {} killed after timeout", name)
+                            }
+                        }
+                    });
+                }
             });
             
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             check_sidecar_health,
+            get_data_root,
+            migrate_data_root,
             get_manifest,
             get_peers,
+            get_peers_typed,
+            ping_peer,
+            validate_manifest,
+            restore_manifest_backup,
             get_feed,
+            get_feed_filtered,
+            append_feed_entry,
+            feed_stats,
+            compact_feed,
+            subscribe_feed,
+            unsubscribe_feed,
             get_resource_state,
+            get_resource_history,
+            pause_resource_monitor,
+            resume_resource_monitor,
+            resource_monitor_paused,
+            get_sidecar_log,
+            get_throttle_config,
+            get_health_config,
+            set_health_config,
+            set_throttle_config,
+            sidecar_health_detail,
             sidecar_status,
             start_sidecar,
-            stop_sidecar
+            start_sidecar_with_env,
+            attach_external_sidecar,
+            stop_sidecar,
+            restart_sidecar,
+            last_sidecar_exit,
+            stop_sidecar_graceful,
+            verify_sidecar,
+            set_sidecar_log_streaming,
+            system_overview,
+            start_metrics_endpoint,
+            stop_metrics_endpoint,
+            reset_mesh_state,
+            app_version,
+            sidecar_version,
+            scan_data_integrity,
+            get_diagnostics_config,
+            set_diagnostics_config,
+            export_diagnostics,
+            send_to_sidecar,
+            follow_sidecar_log,
+            stop_follow_sidecar_log,
+            set_maintenance_mode,
+            maintenance_state,
+            is_readonly,
+            sidecar_port,
+            sidecar_uptime_secs,
+            list_sidecars,
+            get_autostart,
+            set_autostart,
+            hash_data_file,
+            check_sidecar_update,
+            apply_sidecar_update,
+            get_feed_json
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_atomic_writes_leave_one_complete_payload() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("state.json");
+        write_json_atomic(&target, "{}").unwrap();
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let target = target.clone();
+            let done = done.clone();
+            std::thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    let contents = fs::read_to_string(&target).unwrap();
+                    serde_json::from_str::<serde_json::Value>(&contents).unwrap();
+                }
+            })
+        };
+        let writers: Vec<_> = (0..16)
+            .map(|writer| {
+                let target = target.clone();
+                std::thread::spawn(move || {
+                    for round in 0..25 {
+                        let payload = serde_json::json!({
+                            "writer": writer,
+                            "round": round,
+                            "padding": "x".repeat(4096 * (writer % 4 + 1)),
+                        });
+                        write_json_atomic(&target, &payload.to_string()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        reader.join().unwrap();
+
+        let contents = fs::read_to_string(&target).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        let writer = value["writer"].as_u64().unwrap() as usize;
+        assert_eq!(
+            value["padding"].as_str().unwrap().len(),
+            4096 * (writer % 4 + 1)
+        );
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path() != target)
+            .collect();
+        assert!(
+            leftovers.is_empty(),
+            "temp files left behind: {leftovers:?}"
+        );
+    }
+
+    #[test]
+    fn manifest_snapshot_keeps_last_good_copy_as_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let read = |index| fs::read_to_string(backup_path(&path, index)).ok();
+        fs::write(&path, "{\"v\":1}").unwrap();
+        let snapshot = ManifestSnapshot::load(&path);
+
+        fs::write(&path, "{\"v\":").unwrap();
+        snapshot.record_change(&path);
+        fs::write(&path, "not json").unwrap();
+        snapshot.record_change(&path);
+        assert_eq!(read(0).as_deref(), Some("{\"v\":1}\n"));
+        assert_eq!(read(1), None);
+
+        fs::write(&path, "{\"v\":2}").unwrap();
+        snapshot.record_change(&path);
+        fs::write(&path, "{\"v\":3}").unwrap();
+        snapshot.record_change(&path);
+        assert_eq!(read(0).as_deref(), Some("{\"v\":2}\n"));
+        assert_eq!(read(1).as_deref(), Some("{\"v\":1}\n"));
+
+        // A restore over a corrupt manifest backs up the last good copy, not the corrupt one.
+        fs::write(&path, "{\"v\":").unwrap();
+        snapshot.back_up_current(&path).unwrap();
+        assert_eq!(read(0).as_deref(), Some("{\"v\":3}\n"));
+        snapshot.back_up_current(&path).unwrap();
+        assert_eq!(read(1).as_deref(), Some("{\"v\":2}\n"));
+        assert_eq!(read(2).as_deref(), Some("{\"v\":1}\n"));
+    }
+
+    #[test]
+    fn reverse_line_reader_spans_chunks_and_stops_early() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent_feed.jsonl");
+        let lines: Vec<String> = (0..200)
+            .map(|n| format!("{{\"n\":{n},\"text\":\"{}\"}}", "é".repeat(700)))
+            .collect();
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        assert_eq!(tail_lines(&path, 500).unwrap(), lines);
+        assert_eq!(tail_lines(&path, 3).unwrap(), lines[197..]);
+        let mut visited = 0;
+        for_each_line_rev(&path, |_| {
+            visited += 1;
+            visited < 5
+        })
+        .unwrap();
+        assert_eq!(visited, 5);
+
+        // One line spanning many chunks comes back whole.
+        let long = format!(
+            "{{\"text\":\"{}\"}}",
+            "x".repeat(TAIL_CHUNK_BYTES as usize * 5)
+        );
+        fs::write(&path, format!("{{\"n\":0}}\n{long}")).unwrap();
+        assert_eq!(tail_lines(&path, 2).unwrap(), ["{\"n\":0}", long.as_str()]);
+    }
+
+    #[test]
+    fn health_monitor_survives_stop_and_start_as_a_single_task() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("mesh")).unwrap();
+        fs::write(
+            health_config_path(root.path()),
+            r#"{"interval_secs":1,"timeout_secs":1}"#,
+        )
+        .unwrap();
+        std::env::set_var("PROJECT_DAWN_DATA_ROOT", root.path());
+        let app = tauri::test::mock_app();
+
+        tauri::async_runtime::block_on(async {
+            // Every health check is one connection to the sidecar port.
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let probes = Arc::new(AtomicU64::new(0));
+            let counted = probes.clone();
+            let port = listener.local_addr().unwrap().port();
+            tauri::async_runtime::spawn(async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                    drop(socket);
+                }
+            });
+            let state = Arc::new(Mutex::new(SidecarState::new(DEFAULT_SIDECAR_NAME)));
+            state.lock().await.port = port;
+            let running = state.lock().await.health_task_running.clone();
+
+            start_health_monitor(app.handle(), state.clone()).await;
+            for _ in 0..2 {
+                // A quick stop/start keeps the running monitor instead of adding one...
+                state.lock().await.stop_requested = true;
+                state.lock().await.stop_requested = false;
+                start_health_monitor(app.handle(), state.clone()).await;
+                assert!(running.load(Ordering::SeqCst));
+
+                // ...and one that outlives the monitor gets a fresh one.
+                state.lock().await.stop_requested = true;
+                let deadline = Instant::now() + Duration::from_secs(5);
+                while running.load(Ordering::SeqCst) {
+                    assert!(Instant::now() < deadline, "monitor didn't stand down");
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                state.lock().await.stop_requested = false;
+                start_health_monitor(app.handle(), state.clone()).await;
+                assert!(running.load(Ordering::SeqCst));
+            }
+
+            let before = probes.load(Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(3500)).await;
+            let checks = probes.load(Ordering::SeqCst) - before;
+            assert!(
+                (2..=4).contains(&checks),
+                "expected one monitor checking once a second, saw {checks} checks in 3.5s"
+            );
+            state.lock().await.stop_requested = true;
+        });
+    }
+
+    fn run_events(events: Vec<CommandEvent>) -> (SidecarRun, Vec<(String, String)>, usize) {
+        let mut run = SidecarRun::default();
+        let mut logged = Vec::new();
+        let mut handled = 0;
+        for event in events {
+            handled += 1;
+            let more = run.handle_event(event, |stream, line| {
+                logged.push((stream.to_string(), line.to_string()))
+            });
+            if !more {
+                break;
+            }
+        }
+        run.finish(|stream, line| logged.push((stream.to_string(), line.to_string())));
+        (run, logged, handled)
+    }
+
+    #[test]
+    fn sidecar_events_update_the_run_and_the_log() {
+        let (run, logged, handled) = run_events(vec![
+            CommandEvent::Stdout("ready".to_string()),
+            CommandEvent::Stderr("warning".to_string()),
+            CommandEvent::Error("pipe broke".to_string()),
+            CommandEvent::Terminated(tauri::api::process::TerminatedPayload {
+                code: Some(3),
+                signal: None,
+            }),
+            CommandEvent::Stdout("after exit".to_string()),
+        ]);
+        assert_eq!(handled, 4, "events after Terminated are not handled");
+        assert!(run.terminated);
+        assert_eq!(run.exit_status, (Some(3), None));
+        assert_eq!(run.stderr_tail, ["warning"]);
+        let logged: Vec<(&str, &str)> = logged
+            .iter()
+            .map(|(stream, line)| (stream.as_str(), line.as_str()))
+            .collect();
+        assert_eq!(
+            logged,
+            [
+                ("stdout", "ready"),
+                ("stderr", "warning"),
+                ("error", "pipe broke"),
+                ("event", "terminated (code: Some(3), signal: None)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn sidecar_stderr_tail_is_capped_and_a_silent_close_is_logged() {
+        let events = (0..STDERR_TAIL_LINES + 5)
+            .map(|n| CommandEvent::Stderr(format!("line {n}")))
+            .collect();
+        let (run, logged, _) = run_events(events);
+        assert!(!run.terminated);
+        assert_eq!(run.exit_status, (None, None));
+        assert_eq!(run.stderr_tail.len(), STDERR_TAIL_LINES);
+        assert_eq!(run.stderr_tail.front().unwrap(), "line 5");
+        assert_eq!(
+            logged.last().unwrap(),
+            &(
+                "event".to_string(),
+                "event stream closed without a termination status".to_string()
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn atomic_rewrite_keeps_the_target_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.json");
+        write_json_atomic(&path, "{\"v\":1}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        write_json_atomic(&path, "{\"v\":2}").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"v\":2}\n");
+    }
+
+    #[test]
+    fn clean_feed_line_strips_bom_and_carriage_returns() {
+        assert_eq!(clean_feed_line("\u{feff}{\"a\":1}"), Some("{\"a\":1}"));
+        assert_eq!(clean_feed_line("{\"a\":1}\r"), Some("{\"a\":1}"));
+        assert_eq!(clean_feed_line("\u{feff}{\"a\":1}\r\r"), Some("{\"a\":1}"));
+        assert_eq!(clean_feed_line(""), None);
+        assert_eq!(clean_feed_line("   \r"), None);
+        assert_eq!(clean_feed_line("\u{feff}"), None);
+    }
+
+    #[test]
+    fn feed_readers_handle_crlf_bom_and_blank_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent_feed.jsonl");
+        fs::write(
+            &path,
+            "\u{feff}{\"n\":1}\r\n\r\n{\"n\":2}\r\n\n  \n{\"n\":3}\r",
+        )
+        .unwrap();
+        let expected = ["{\"n\":1}", "{\"n\":2}", "{\"n\":3}"];
+
+        assert_eq!(tail_lines(&path, 10).unwrap(), expected);
+        assert_eq!(tail_lines(&path, 2).unwrap(), expected[1..]);
+        // The delta reader leaves the unterminated last line for the next call.
+        let mut offset = 0;
+        assert_eq!(read_feed_delta(&path, &mut offset).unwrap(), expected[..2]);
+    }
+
+    #[test]
+    fn resolve_under_rejects_parent_and_absolute_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for relative in ["", "..", "vault/../../etc/passwd", "vault/.."] {
+            assert!(
+                matches!(resolve_under(root, relative), Err(AppError::InvalidPath(_))),
+                "{relative:?} should be rejected"
+            );
+        }
+        let absolute = if cfg!(windows) {
+            "C:\\Windows\\win.ini"
+        } else {
+            "/etc/passwd"
+        };
+        assert!(matches!(
+            resolve_under(root, absolute),
+            Err(AppError::InvalidPath(_))
+        ));
+    }
+
+    #[test]
+    fn resolve_under_allows_paths_that_do_not_exist_yet() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("vault")).unwrap();
+        assert_eq!(
+            resolve_under(&root, "vault/new/manifest.json").unwrap(),
+            root.join("vault").join("new").join("manifest.json")
+        );
+        assert_eq!(
+            resolve_under(&root, "./mesh/peers.json").unwrap(),
+            root.join("mesh").join("peers.json")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_under_follows_symlinks_only_inside_the_root() {
+        use std::os::unix::fs::symlink;
+
+        let outside = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("vault")).unwrap();
+        fs::write(root.join("vault").join("manifest.json"), "{}").unwrap();
+        symlink(root.join("vault"), root.join("inside")).unwrap();
+        symlink(outside.path(), root.join("escape")).unwrap();
+        symlink(root.join("missing"), root.join("dangling")).unwrap();
+
+        assert_eq!(
+            resolve_under(&root, "inside/manifest.json").unwrap(),
+            root.join("vault").join("manifest.json")
+        );
+        assert!(matches!(
+            resolve_under(&root, "escape/secret"),
+            Err(AppError::InvalidPath(_))
+        ));
+        assert!(resolve_under(&root, "dangling").is_err());
+        assert!(resolve_under(&root, "dangling/file").is_err());
+    }
+
+    #[test]
+    fn feed_cut_keeps_a_suffix_starting_at_a_line() {
+        let feed = b"{\"n\":1}\n\n{\"n\":2}\r\n{\"n\":3}\n  \n{\"n\":4}\n";
+        let kept = |keep| std::str::from_utf8(&feed[feed_cut(feed, keep)..]).unwrap();
+
+        assert_eq!(kept(FeedKeep::Entries(2)), "{\"n\":3}\n  \n{\"n\":4}\n");
+        assert_eq!(
+            kept(FeedKeep::Entries(4)),
+            std::str::from_utf8(feed).unwrap()
+        );
+        assert_eq!(
+            kept(FeedKeep::Entries(10)),
+            std::str::from_utf8(feed).unwrap()
+        );
+        assert_eq!(kept(FeedKeep::Entries(0)), "");
+        // Mid-line budgets round forward to the next line start.
+        assert_eq!(kept(FeedKeep::Bytes(12)), "  \n{\"n\":4}\n");
+        assert_eq!(kept(FeedKeep::Bytes(10)), "{\"n\":4}\n");
+        assert_eq!(
+            kept(FeedKeep::Bytes(1000)),
+            std::str::from_utf8(feed).unwrap()
+        );
+    }
+
+    #[test]
+    fn transient_read_errors_are_retried() {
+        let busy = || std::io::Error::from(ErrorKind::WouldBlock);
+        let mut calls = 0;
+        let result = retry_transient(|| {
+            calls += 1;
+            if calls < READ_RETRY_ATTEMPTS {
+                Err(busy())
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), READ_RETRY_ATTEMPTS);
+
+        let mut calls = 0;
+        let result: std::io::Result<()> = retry_transient(|| {
+            calls += 1;
+            Err(busy())
+        });
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert_eq!(calls, READ_RETRY_ATTEMPTS);
+
+        let mut calls = 0;
+        let result: std::io::Result<()> = retry_transient(|| {
+            calls += 1;
+            Err(std::io::Error::from(ErrorKind::PermissionDenied))
+        });
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn update_rollback_reports_files_it_could_not_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("dawn-sidecar");
+        let checksum = dir.path().join("dawn-sidecar.sha256");
+        fs::write(&binary, "old binary").unwrap();
+        fs::write(&checksum, "old checksum").unwrap();
+        let mut rollback = UpdateRollback(Vec::new());
+        rollback.save(&binary).unwrap();
+        rollback.save(&checksum).unwrap();
+        fs::write(&binary, "new binary").unwrap();
+        // A non-empty directory where the checksum was can't be renamed over.
+        fs::remove_file(&checksum).unwrap();
+        fs::create_dir(&checksum).unwrap();
+        fs::write(checksum.join("busy"), "").unwrap();
+
+        let err = rollback.restore().unwrap_err();
+        assert!(matches!(err, AppError::RollbackFailed(_)));
+        assert!(err.message().contains("dawn-sidecar.sha256.rollback"));
+        assert_eq!(fs::read_to_string(&binary).unwrap(), "old binary");
+        assert!(!dir.path().join("dawn-sidecar.rollback").exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("dawn-sidecar.sha256.rollback")).unwrap(),
+            "old checksum"
+        );
+    }
+}